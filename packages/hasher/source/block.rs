@@ -0,0 +1,122 @@
+//! Block buffering and Merkle–Damgård strengthening shared by the hashers.
+
+/// Collects input into `N`-byte blocks and hands every complete block to the
+/// compression function. Whole blocks are taken straight from the input, only
+/// a trailing partial block is copied.
+#[derive(Clone, Debug)]
+pub struct BlockBuffer<const N: usize> {
+    buffer: [u8; N],
+    buffer_len: usize,
+    length: u128,
+}
+
+impl<const N: usize> Default for BlockBuffer<N> {
+    fn default() -> Self {
+        BlockBuffer::new()
+    }
+}
+
+impl<const N: usize> BlockBuffer<N> {
+    pub fn new() -> Self {
+        BlockBuffer {
+            buffer: [0u8; N],
+            buffer_len: 0,
+            length: 0,
+        }
+    }
+
+    /// Restores a buffer that was fed `length` bytes, `buffered` being the
+    /// trailing partial block.
+    ///
+    /// # Panics
+    ///
+    /// When `buffered` is not `length % N` bytes long.
+    pub fn from_parts(length: u128, buffered: &[u8]) -> Self {
+        assert_eq!(
+            length % N as u128,
+            buffered.len() as u128,
+            "Buffered bytes must be the partial block of the fed length"
+        );
+
+        let mut buffer = [0u8; N];
+        buffer[..buffered.len()].copy_from_slice(buffered);
+
+        BlockBuffer {
+            buffer,
+            buffer_len: buffered.len(),
+            length,
+        }
+    }
+
+    /// Number of bytes fed so far.
+    pub fn length(&self) -> u128 {
+        self.length
+    }
+
+    /// The partial block waiting for more input.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[..self.buffer_len]
+    }
+
+    pub fn update(&mut self, mut data: &[u8], mut compress: impl FnMut(&[u8; N])) {
+        self.length = self.length.wrapping_add(data.len() as u128);
+
+        if self.buffer_len > 0 {
+            let take = (N - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len < N {
+                return;
+            }
+
+            compress(&self.buffer);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(N);
+
+        for block in &mut blocks {
+            compress(block.try_into().unwrap());
+        }
+
+        let remainder = blocks.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffer_len = remainder.len();
+    }
+
+    /// Appends the `0x80` marker, zero bytes and the encoded message length
+    /// so that the last block ends with `encoded_length`, spilling into one
+    /// more block when the current one has no room left.
+    pub fn pad(self, encoded_length: &[u8], compress: impl FnMut(&[u8; N])) {
+        self.pad_bits(0, 0, encoded_length, compress)
+    }
+
+    /// Like [`BlockBuffer::pad`] for a message ending with the `bit_count`
+    /// high bits of `partial_byte`, the marker bit following them in the same
+    /// byte.
+    pub fn pad_bits(
+        mut self,
+        partial_byte: u8,
+        bit_count: u32,
+        encoded_length: &[u8],
+        mut compress: impl FnMut(&[u8; N]),
+    ) {
+        assert!(bit_count < 8, "A partial byte has at most 7 bits");
+
+        let length_offset = N - encoded_length.len();
+        let kept_bits = !(0xffu8 >> bit_count);
+
+        self.buffer[self.buffer_len] = (partial_byte & kept_bits) | (0x80 >> bit_count);
+        self.buffer[self.buffer_len + 1..].fill(0);
+
+        if self.buffer_len >= length_offset {
+            compress(&self.buffer);
+            self.buffer.fill(0);
+        }
+
+        self.buffer[length_offset..].copy_from_slice(encoded_length);
+        compress(&self.buffer);
+    }
+}
@@ -0,0 +1,63 @@
+//! File names in the checksum lines of GNU coreutils, as written by `md5sum`
+//! and read back by `md5sum -c`.
+//!
+//! A name with a backslash, line feed or carriage return is escaped and its
+//! line starts with a backslash, so every entry stays on a single line.
+
+use std::string::{String, ToString};
+
+/// Escapes `name`: a backslash, line feed or carriage return becomes `\\`,
+/// `\n` or `\r`. The returned flag tells whether anything was escaped, in
+/// which case the line has to start with a backslash.
+pub fn escape(name: &str) -> (bool, String) {
+    if !name.contains(['\\', '\n', '\r']) {
+        return (false, name.to_string());
+    }
+
+    let escaped = name
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+
+    (true, escaped)
+}
+
+/// Inverse of [`escape`], `None` for an unknown or unfinished escape.
+pub fn unescape(name: &str) -> Option<String> {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            result.push(char);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => result.push('\\'),
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_like_coreutils() {
+        assert_eq!((false, "plain name".to_string()), escape("plain name"));
+        assert_eq!((true, "a\\\\b\\nc\\r".to_string()), escape("a\\b\nc\r"));
+
+        for name in ["plain", "back\\slash", "two\nlines", "\r\n\\"] {
+            assert_eq!(Some(name.to_string()), unescape(&escape(name).1));
+        }
+
+        assert_eq!(None, unescape("trailing\\"));
+        assert_eq!(None, unescape("unknown\\t"));
+    }
+}
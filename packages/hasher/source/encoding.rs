@@ -0,0 +1,343 @@
+//! Hex and RFC 4648 base64 codecs for digests.
+//!
+//! Encoders write to any [`fmt::Write`] and decoders fill a caller buffer, so
+//! both work without an allocator. Base64 is encoded with padding, base64url
+//! without, and both are accepted with or without padding when decoding.
+//! Non-zero bits after the last decoded byte are rejected so every digest has
+//! a single valid encoding.
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The text does not decode to the expected number of bytes.
+    InvalidLength,
+    InvalidCharacter(char),
+    /// Misplaced `=` or non-zero trailing bits.
+    InvalidPadding,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::InvalidLength => write!(formatter, "Invalid encoded digest length"),
+            DecodeError::InvalidCharacter(char) => {
+                write!(formatter, "Invalid character {:?} in encoded digest", char)
+            }
+            DecodeError::InvalidPadding => write!(formatter, "Invalid base64 padding"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base64 {
+    /// `+` and `/` for the last two digits, padded with `=`.
+    Standard,
+    /// `-` and `_` for the last two digits, unpadded.
+    UrlSafe,
+}
+
+impl Base64 {
+    fn alphabet(self) -> &'static [u8; 64] {
+        match self {
+            Base64::Standard => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+            Base64::UrlSafe => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+        }
+    }
+
+    fn value(self, char: u8) -> Result<u32, DecodeError> {
+        self.alphabet()
+            .iter()
+            .position(|&digit| digit == char)
+            .map(|value| value as u32)
+            .ok_or(DecodeError::InvalidCharacter(char as char))
+    }
+}
+
+pub fn encode_hex(data: &[u8], output: &mut impl fmt::Write) -> fmt::Result {
+    data.iter()
+        .try_for_each(|byte| write!(output, "{:02x}", byte))
+}
+
+/// Displays bytes as lowercase hex, without allocating.
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        encode_hex(self.0, formatter)
+    }
+}
+
+/// Decodes `text`, in either case, into exactly `output.len()` bytes.
+pub fn decode_hex(text: &str, output: &mut [u8]) -> Result<(), DecodeError> {
+    if text.len() != output.len() * 2 {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    let digit = |char: u8| {
+        (char as char)
+            .to_digit(16)
+            .map(|value| value as u8)
+            .ok_or(DecodeError::InvalidCharacter(char as char))
+    };
+
+    for (byte, pair) in output.iter_mut().zip(text.as_bytes().chunks_exact(2)) {
+        *byte = digit(pair[0])? << 4 | digit(pair[1])?;
+    }
+
+    Ok(())
+}
+
+pub fn encode_base64(data: &[u8], variant: Base64, output: &mut impl fmt::Write) -> fmt::Result {
+    let alphabet = variant.alphabet();
+
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+        for index in 0..chunk.len() + 1 {
+            let value = (bits >> (18 - 6 * index)) & 0x3f;
+            output.write_char(alphabet[value as usize] as char)?;
+        }
+
+        if variant == Base64::Standard {
+            for _ in chunk.len()..3 {
+                output.write_char('=')?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of bytes `text` decodes to, without validating its characters.
+pub fn base64_decoded_len(text: &str) -> Result<usize, DecodeError> {
+    let unpadded = text.trim_end_matches('=');
+    let padding = text.len() - unpadded.len();
+
+    if padding > 2 || (padding > 0 && !text.len().is_multiple_of(4)) {
+        return Err(DecodeError::InvalidPadding);
+    }
+
+    match unpadded.len() % 4 {
+        1 => Err(DecodeError::InvalidLength),
+        rest => Ok(unpadded.len() / 4 * 3 + rest.saturating_sub(1)),
+    }
+}
+
+/// Decodes `text` into exactly `output.len()` bytes.
+pub fn decode_base64(text: &str, variant: Base64, output: &mut [u8]) -> Result<(), DecodeError> {
+    if base64_decoded_len(text)? != output.len() {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    let digits = text.trim_end_matches('=').as_bytes();
+
+    for (chunk, bytes) in digits.chunks(4).zip(output.chunks_mut(3)) {
+        let mut bits = 0u32;
+
+        for (index, &char) in chunk.iter().enumerate() {
+            bits |= variant.value(char)? << (18 - 6 * index);
+        }
+
+        let group = bits.to_be_bytes();
+        bytes.copy_from_slice(&group[1..1 + bytes.len()]);
+
+        if group[1 + bytes.len()..].iter().any(|&byte| byte != 0) {
+            return Err(DecodeError::InvalidPadding);
+        }
+    }
+
+    Ok(())
+}
+
+/// Serde helpers for digest types: hex strings in human-readable formats such
+/// as JSON, raw bytes in binary ones.
+#[cfg(feature = "serde")]
+pub mod serde {
+    use core::fmt;
+    use core::marker::PhantomData;
+    use core::str::FromStr;
+
+    use ::serde::{de, Deserializer, Serializer};
+
+    use super::{DecodeError, Hex};
+
+    pub fn serialize<S: Serializer>(digest: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match serializer.is_human_readable() {
+            true => serializer.collect_str(&Hex(digest)),
+            false => serializer.serialize_bytes(digest),
+        }
+    }
+
+    struct DigestVisitor<T>(PhantomData<T>);
+
+    impl<T> de::Visitor<'_> for DigestVisitor<T>
+    where
+        T: FromStr<Err = DecodeError> + for<'a> TryFrom<&'a [u8], Error = DecodeError>,
+    {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a hex encoded digest or its bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<T, E> {
+            text.parse().map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<T, E> {
+            T::try_from(bytes).map_err(E::custom)
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr<Err = DecodeError> + for<'a> TryFrom<&'a [u8], Error = DecodeError>,
+    {
+        let visitor = DigestVisitor(PhantomData);
+
+        match deserializer.is_human_readable() {
+            true => deserializer.deserialize_str(visitor),
+            false => deserializer.deserialize_bytes(visitor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Formats into a fixed buffer, the crate has no allocator.
+    struct Buffer {
+        bytes: [u8; 64],
+        len: usize,
+    }
+
+    impl Buffer {
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl fmt::Write for Buffer {
+        fn write_str(&mut self, text: &str) -> fmt::Result {
+            self.bytes[self.len..self.len + text.len()].copy_from_slice(text.as_bytes());
+            self.len += text.len();
+            Ok(())
+        }
+    }
+
+    fn encoded(encode: impl Fn(&mut Buffer) -> fmt::Result) -> Buffer {
+        let mut buffer = Buffer {
+            bytes: [0u8; 64],
+            len: 0,
+        };
+        encode(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn rfc_4648_base64_test_vectors() {
+        let test_cases: &[(&str, &str, &str)] = &[
+            ("", "", ""),
+            ("f", "Zg==", "Zg"),
+            ("fo", "Zm8=", "Zm8"),
+            ("foo", "Zm9v", "Zm9v"),
+            ("foob", "Zm9vYg==", "Zm9vYg"),
+            ("fooba", "Zm9vYmE=", "Zm9vYmE"),
+            ("foobar", "Zm9vYmFy", "Zm9vYmFy"),
+        ];
+
+        for &(data, standard, url_safe) in test_cases {
+            let data = data.as_bytes();
+            let mut decoded = [0u8; 6];
+            let decoded = &mut decoded[..data.len()];
+
+            let buffer = encoded(|output| encode_base64(data, Base64::Standard, output));
+            assert_eq!(standard, buffer.as_str());
+            let buffer = encoded(|output| encode_base64(data, Base64::UrlSafe, output));
+            assert_eq!(url_safe, buffer.as_str());
+
+            for text in [standard, url_safe] {
+                decode_base64(text, Base64::Standard, decoded).unwrap();
+                assert_eq!(data, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn base64_alphabets_differ_in_two_digits() {
+        let data = [0xfb, 0xff];
+        let mut decoded = [0u8; 2];
+
+        assert_eq!(
+            "+/8=",
+            encoded(|output| encode_base64(&data, Base64::Standard, output)).as_str()
+        );
+        assert_eq!(
+            "-_8",
+            encoded(|output| encode_base64(&data, Base64::UrlSafe, output)).as_str()
+        );
+
+        decode_base64("-_8", Base64::UrlSafe, &mut decoded).unwrap();
+        assert_eq!(data, decoded);
+        assert_eq!(
+            Err(DecodeError::InvalidCharacter('-')),
+            decode_base64("-_8", Base64::Standard, &mut decoded)
+        );
+    }
+
+    #[test]
+    fn malformed_base64_is_rejected() {
+        let mut decoded = [0u8; 2];
+
+        assert_eq!(
+            Err(DecodeError::InvalidPadding),
+            decode_base64("Zm9", Base64::Standard, &mut decoded)
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidPadding),
+            decode_base64("Zm8==", Base64::Standard, &mut decoded)
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            decode_base64("Zm9vY", Base64::Standard, &mut decoded)
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            decode_base64("Zg==", Base64::Standard, &mut decoded)
+        );
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let data = [0x00, 0x7f, 0x80, 0xff];
+        let mut decoded = [0u8; 4];
+
+        assert_eq!(
+            "007f80ff",
+            encoded(|output| encode_hex(&data, output)).as_str()
+        );
+        assert_eq!(
+            "007f80ff",
+            encoded(|output| fmt::write(output, format_args!("{}", Hex(&data)))).as_str()
+        );
+
+        decode_hex("007F80fF", &mut decoded).unwrap();
+        assert_eq!(data, decoded);
+
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            decode_hex("007f80f", &mut decoded)
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidCharacter('g')),
+            decode_hex("007f80fg", &mut decoded)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! HMAC as specified in RFC 2104, over any [`Hasher`].
+
+use crate::{ct_eq, Hasher};
+
+/// Largest block size of the supported hashers, SHA-512 uses 128 byte blocks.
+const MAX_BLOCK_SIZE: usize = 128;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+#[derive(Clone)]
+pub struct Hmac<H: Hasher> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: Hasher> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        assert!(
+            H::BLOCK_SIZE <= MAX_BLOCK_SIZE,
+            "Block size of the hasher is too large for HMAC"
+        );
+
+        let mut block_key = [0u8; MAX_BLOCK_SIZE];
+        let block_key = &mut block_key[..H::BLOCK_SIZE];
+
+        if key.len() > H::BLOCK_SIZE {
+            let key_digest = H::digest(key);
+            let key_digest = key_digest.as_ref();
+            block_key[..key_digest.len()].copy_from_slice(key_digest);
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut pad = [0u8; MAX_BLOCK_SIZE];
+        let pad = &mut pad[..H::BLOCK_SIZE];
+
+        let mut inner = H::new();
+        pad.iter_mut()
+            .zip(block_key.iter())
+            .for_each(|(pad, key)| *pad = key ^ IPAD);
+        inner.update(pad);
+
+        let mut outer = H::new();
+        pad.iter_mut()
+            .zip(block_key.iter())
+            .for_each(|(pad, key)| *pad = key ^ OPAD);
+        outer.update(pad);
+
+        Hmac { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> H::Output {
+        let Hmac { inner, mut outer } = self;
+
+        outer.update(inner.finalize().as_ref());
+        outer.finalize()
+    }
+
+    /// Checks the computed tag against `tag` without leaking through timing
+    /// how many leading bytes matched.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        ct_eq(self.finalize().as_ref(), tag)
+    }
+
+    pub fn mac(key: &[u8], data: &[u8]) -> H::Output {
+        let mut hmac = Self::new(key);
+        hmac.update(data);
+        hmac.finalize()
+    }
+}
@@ -0,0 +1,60 @@
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod block;
+#[cfg(feature = "std")]
+pub mod checksum_file;
+pub mod encoding;
+pub mod hmac;
+#[cfg(feature = "std")]
+pub mod tree;
+
+pub use block::BlockBuffer;
+pub use hmac::Hmac;
+
+/// Common interface of the incremental hash functions in this repository.
+pub trait Hasher: Clone {
+    /// Size in bytes of the blocks fed to the compression function.
+    const BLOCK_SIZE: usize;
+
+    type Output: AsRef<[u8]> + Clone;
+
+    fn new() -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Self::Output;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// Compares two byte strings in time that depends only on their lengths.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let difference = a.iter().zip(b).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+
+    core::hint::black_box(difference) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ct_eq;
+
+    #[test]
+    fn constant_time_equality() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"digest", b"digest"));
+        assert!(!ct_eq(b"digest", b"digesT"));
+        assert!(!ct_eq(b"digest", b"digest!"));
+        assert!(!ct_eq(&[0x80], &[0x00]));
+    }
+}
@@ -0,0 +1,449 @@
+//! Directory tree hashing with Merkle manifests.
+//!
+//! Every regular file below the root directory is hashed, subdirectories are
+//! walked and other entries such as symbolic links are skipped. Paths are
+//! relative to the root, `/`-separated and sorted bytewise.
+//!
+//! The Merkle root combines the leaves `H(0x00 || path || 0x00 || digest)`
+//! pairwise as `H(0x01 || left || right)`, an odd node being promoted
+//! unchanged to the next level. The prefixes keep leaves and inner nodes
+//! apart, and hashing the path makes a rename change the root. The root of an
+//! empty tree is the digest of the empty string.
+//!
+//! A manifest is the algorithm name, the root and one `digest  path` line per
+//! file, the same layout as `md5sum`:
+//!
+//! ```text
+//! algorithm md5
+//! root 849baa73d44a0d0ef606358809fda46c
+//! d41d8cd98f00b204e9800998ecf8427e  empty.txt
+//! 0cc175b9c0f1b6a831c399e269772661  nested/a.txt
+//! ```
+//!
+//! A path with a backslash or a line break is escaped and its line starts with
+//! a backslash, as GNU coreutils does.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::string::{String, ToString};
+use std::vec;
+use std::vec::Vec;
+
+use crate::checksum_file;
+use crate::encoding::{self, Hex};
+use crate::Hasher;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    MissingAlgorithm,
+    MissingRoot,
+    /// 1-based number of a line that is not a valid entry.
+    InvalidLine(usize),
+    /// 1-based number of a line repeating the path of an earlier one.
+    DuplicatePath(usize),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestError::MissingAlgorithm => write!(formatter, "Manifest has no algorithm line"),
+            ManifestError::MissingRoot => write!(formatter, "Manifest has no root line"),
+            ManifestError::InvalidLine(line) => write!(formatter, "Invalid manifest line {}", line),
+            ManifestError::DuplicatePath(line) => {
+                write!(formatter, "Manifest line {} repeats a path", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Differences between a manifest and the current state of the tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    algorithm: String,
+    files: BTreeMap<String, Vec<u8>>,
+    root: Vec<u8>,
+}
+
+fn hash_file<H: Hasher>(path: &Path) -> io::Result<H::Output> {
+    let mut file = File::open(path)?;
+    let mut hasher = H::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn walk<H: Hasher>(
+    directory: &Path,
+    prefix: &str,
+    exclude: &[&str],
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                std::format!("{:?}: file name is not valid UTF-8", name),
+            )
+        })?;
+
+        let path = std::format!("{}{}", prefix, name);
+        if exclude.contains(&path.as_str()) {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            walk::<H>(&entry.path(), &std::format!("{}/", path), exclude, files)?;
+        } else if file_type.is_file() {
+            let digest = hash_file::<H>(&entry.path())?;
+            files.insert(path, digest.as_ref().to_vec());
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the Merkle root of sorted `(path, digest)` leaves.
+pub fn merkle_root<H: Hasher>(files: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut level: Vec<Vec<u8>> = files
+        .iter()
+        .map(|(path, digest)| {
+            let mut hasher = H::new();
+            hasher.update(&[0x00]);
+            hasher.update(path.as_bytes());
+            hasher.update(&[0x00]);
+            hasher.update(digest);
+            hasher.finalize().as_ref().to_vec()
+        })
+        .collect();
+
+    if level.is_empty() {
+        return H::digest(&[]).as_ref().to_vec();
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = H::new();
+                    hasher.update(&[0x01]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize().as_ref().to_vec()
+                }
+                [single] => single.clone(),
+                _ => unreachable!("Chunks hold one or two nodes"),
+            })
+            .collect();
+    }
+
+    level.pop().unwrap()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let mut bytes = vec![0u8; hex.len() / 2];
+    encoding::decode_hex(hex, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+impl Manifest {
+    /// Hashes every file below `directory`, `algorithm` is the name recorded
+    /// in the manifest. Paths listed in `exclude`, relative to `directory`,
+    /// are left out, such as the manifest itself.
+    pub fn build<H: Hasher>(
+        algorithm: &str,
+        directory: &Path,
+        exclude: &[&str],
+    ) -> io::Result<Manifest> {
+        let mut files = BTreeMap::new();
+        walk::<H>(directory, "", exclude, &mut files)?;
+
+        Ok(Manifest::from_files::<H>(algorithm, files))
+    }
+
+    pub fn from_files<H: Hasher>(algorithm: &str, files: BTreeMap<String, Vec<u8>>) -> Manifest {
+        Manifest {
+            algorithm: algorithm.to_string(),
+            root: merkle_root::<H>(&files),
+            files,
+        }
+    }
+
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.root
+    }
+
+    /// Digests by path.
+    pub fn files(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.files
+    }
+
+    /// Parses a manifest, its root is taken as is, see [`Manifest::has_valid_root`].
+    pub fn parse(text: &str) -> Result<Manifest, ManifestError> {
+        let mut lines = text.lines().enumerate();
+
+        let algorithm = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("algorithm "))
+            .filter(|algorithm| !algorithm.is_empty())
+            .ok_or(ManifestError::MissingAlgorithm)?;
+
+        let root = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("root "))
+            .and_then(decode_hex)
+            .ok_or(ManifestError::MissingRoot)?;
+
+        let mut files = BTreeMap::new();
+
+        for (index, line) in lines {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let (escaped, line) = match line.strip_prefix('\\') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+
+            let entry = line.split_once("  ").and_then(|(digest, path)| {
+                let path = match escaped {
+                    true => checksum_file::unescape(path)?,
+                    false => path.to_string(),
+                };
+                Some((decode_hex(digest)?, path)).filter(|(_, path)| !path.is_empty())
+            });
+
+            let Some((digest, path)) = entry else {
+                return Err(ManifestError::InvalidLine(index + 1));
+            };
+
+            if files.insert(path, digest).is_some() {
+                return Err(ManifestError::DuplicatePath(index + 1));
+            }
+        }
+
+        Ok(Manifest {
+            algorithm: algorithm.to_string(),
+            files,
+            root,
+        })
+    }
+
+    /// Whether the recorded root matches the file digests, a mismatch means
+    /// the manifest was edited or corrupted.
+    pub fn has_valid_root<H: Hasher>(&self) -> bool {
+        crate::ct_eq(&merkle_root::<H>(&self.files), &self.root)
+    }
+
+    /// Lists what changed from this manifest to `current`, usually built from
+    /// the tree being verified.
+    pub fn compare(&self, current: &Manifest) -> Changes {
+        let mut changes = Changes::default();
+
+        for (path, digest) in &self.files {
+            match current.files.get(path) {
+                None => changes.removed.push(path.clone()),
+                Some(other) if !crate::ct_eq(digest, other) => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+
+        changes.added = current
+            .files
+            .keys()
+            .filter(|path| !self.files.contains_key(*path))
+            .cloned()
+            .collect();
+
+        changes
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(formatter, "algorithm {}", self.algorithm)?;
+        writeln!(formatter, "root {}", Hex(&self.root))?;
+
+        for (path, digest) in &self.files {
+            let (escaped, path) = checksum_file::escape(path);
+            let prefix = if escaped { "\\" } else { "" };
+            writeln!(formatter, "{}{}  {}", prefix, Hex(digest), path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A toy hasher, the sum of the bytes with the length, enough to exercise
+    /// the tree logic without depending on a real hash crate.
+    #[derive(Clone)]
+    struct Sum(u8, u8);
+
+    impl Hasher for Sum {
+        const BLOCK_SIZE: usize = 1;
+        type Output = [u8; 2];
+
+        fn new() -> Self {
+            Sum(0, 0)
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            for &byte in data {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(byte);
+                self.1 = self.1.wrapping_add(1);
+            }
+        }
+
+        fn finalize(self) -> [u8; 2] {
+            [self.0, self.1]
+        }
+    }
+
+    fn temporary_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(std::format!("hasher-tree-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("nested/deeper")).unwrap();
+        directory
+    }
+
+    #[test]
+    fn merkle_root_promotes_odd_nodes() {
+        let files: BTreeMap<String, Vec<u8>> = ["a", "b", "c"]
+            .iter()
+            .map(|path| (path.to_string(), vec![path.as_bytes()[0]]))
+            .collect();
+
+        let leaf = |path: &str| {
+            let mut hasher = Sum::new();
+            hasher.update(&[0x00]);
+            hasher.update(path.as_bytes());
+            hasher.update(&[0x00]);
+            hasher.update(path.as_bytes());
+            hasher.finalize()
+        };
+        let node = |left: [u8; 2], right: [u8; 2]| {
+            let mut hasher = Sum::new();
+            hasher.update(&[0x01]);
+            hasher.update(&left);
+            hasher.update(&right);
+            hasher.finalize()
+        };
+
+        let expected = node(node(leaf("a"), leaf("b")), leaf("c"));
+        assert_eq!(expected.to_vec(), merkle_root::<Sum>(&files));
+        assert_eq!(
+            Sum::digest(&[]).to_vec(),
+            merkle_root::<Sum>(&BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn manifest_round_trip_and_changes() {
+        let directory = temporary_directory("changes");
+        fs::write(directory.join("a.txt"), b"a").unwrap();
+        fs::write(directory.join("nested/b.txt"), b"b").unwrap();
+        fs::write(directory.join("nested/deeper/c\\d.txt"), b"c").unwrap();
+        fs::write(directory.join("MANIFEST"), b"ignored").unwrap();
+
+        let manifest = Manifest::build::<Sum>("sum", &directory, &["MANIFEST"]).unwrap();
+        let paths: Vec<&str> = manifest.files().keys().map(String::as_str).collect();
+        assert_eq!(
+            vec!["a.txt", "nested/b.txt", "nested/deeper/c\\d.txt"],
+            paths
+        );
+
+        let text = manifest.to_string();
+        assert!(text.contains("\\6301  nested/deeper/c\\\\d.txt\n"));
+
+        let parsed = Manifest::parse(&text).unwrap();
+        assert_eq!(manifest, parsed);
+        assert!(parsed.has_valid_root::<Sum>());
+
+        fs::write(directory.join("a.txt"), b"A").unwrap();
+        fs::remove_file(directory.join("nested/b.txt")).unwrap();
+        fs::write(directory.join("nested/new.txt"), b"new").unwrap();
+
+        let current = Manifest::build::<Sum>("sum", &directory, &["MANIFEST"]).unwrap();
+        let changes = manifest.compare(&current);
+        assert_eq!(vec!["nested/new.txt"], changes.added);
+        assert_eq!(vec!["nested/b.txt"], changes.removed);
+        assert_eq!(vec!["a.txt"], changes.modified);
+        assert_ne!(manifest.root(), current.root());
+        assert!(manifest.compare(&manifest).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        let tampered = "algorithm sum\nroot 0000\n6100  a.txt\n";
+        assert!(!Manifest::parse(tampered).unwrap().has_valid_root::<Sum>());
+
+        let test_cases: &[(&str, ManifestError)] = &[
+            ("", ManifestError::MissingAlgorithm),
+            ("algorithm sum\n", ManifestError::MissingRoot),
+            ("algorithm sum\nroot 0\n", ManifestError::MissingRoot),
+            (
+                "algorithm sum\nroot 00\n61 a.txt\n",
+                ManifestError::InvalidLine(3),
+            ),
+            (
+                "algorithm sum\nroot 00\n6100  \n",
+                ManifestError::InvalidLine(3),
+            ),
+            (
+                "algorithm sum\nroot 00\n6100  a\n6200  a\n",
+                ManifestError::DuplicatePath(4),
+            ),
+        ];
+
+        for (text, expected) in test_cases {
+            assert_eq!(
+                Err(expected.clone()),
+                Manifest::parse(text),
+                "Failed: {:?}",
+                text
+            );
+        }
+    }
+}
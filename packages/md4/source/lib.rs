@@ -0,0 +1,221 @@
+//! From-scratch MD4 as specified in RFC 1320, and the NTLM password hash
+//! built on it.
+//!
+//! MD4 is broken and only kept to interoperate with NTLM.
+
+use std::fmt;
+
+use hasher::BlockBuffer;
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Digest(pub [u8; 16]);
+
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, formatter)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// Message word used by every step of the three rounds.
+const ORDER: [[usize; 16]; 3] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+    [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+];
+
+/// Rotations of the four consecutive steps, repeated through every round.
+const SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+
+/// Additive constants of the rounds: zero, `sqrt(2)` and `sqrt(3)`.
+const K: [u32; 3] = [0, 0x5a827999, 0x6ed9eba1];
+
+fn compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut x = [0u32; 16];
+
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let mut registers = *state;
+
+    for round in 0..3 {
+        for step in 0..16 {
+            let [a, b, c, d] = registers;
+
+            let f = match round {
+                0 => (b & c) | (!b & d),
+                1 => (b & c) | (b & d) | (c & d),
+                _ => b ^ c ^ d,
+            };
+
+            let a = a
+                .wrapping_add(f)
+                .wrapping_add(x[ORDER[round][step]])
+                .wrapping_add(K[round])
+                .rotate_left(SHIFTS[round][step % 4]);
+
+            // The next step updates d, then c, then b, so the registers
+            // rotate right by one after each step.
+            registers = [d, a, b, c];
+        }
+    }
+
+    for (word, value) in state.iter_mut().zip(registers) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// Incremental MD4 hasher.
+#[derive(Clone, Debug)]
+pub struct Md4 {
+    state: [u32; 4],
+    buffer: BlockBuffer<64>,
+}
+
+impl Default for Md4 {
+    fn default() -> Self {
+        Md4::new()
+    }
+}
+
+impl Md4 {
+    pub fn new() -> Self {
+        Md4 {
+            state: INITIAL_STATE,
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> Digest {
+        let data_len_bits = (self.buffer.length() as u64).wrapping_mul(8);
+        let state = &mut self.state;
+        self.buffer
+            .pad(&data_len_bits.to_le_bytes(), |block| compress(state, block));
+
+        let mut result = [0u8; 16];
+        for (bytes, word) in result.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        Digest(result)
+    }
+}
+
+impl hasher::Hasher for Md4 {
+    const BLOCK_SIZE: usize = 64;
+
+    type Output = Digest;
+
+    fn new() -> Self {
+        Md4::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Md4::update(self, data)
+    }
+
+    fn finalize(self) -> Digest {
+        Md4::finalize(self)
+    }
+}
+
+pub fn digest(input: &[u8]) -> Digest {
+    let mut hasher = Md4::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// NT hash of a password, the MD4 of its UTF-16LE encoding, as stored by
+/// Windows and used as the NTLM key.
+pub fn ntlm(password: &str) -> Digest {
+    let mut hasher = Md4::new();
+
+    for unit in password.encode_utf16() {
+        hasher.update(&unit.to_le_bytes());
+    }
+
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_1320_test_suite() {
+        let test_cases: &[(&str, &str)] = &[
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+
+        for &(input, expected) in test_cases {
+            assert_eq!(
+                expected,
+                digest(input.as_bytes()).to_string(),
+                "Failed input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let data: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let expected = digest(&data);
+
+        for chunk_len in 1..=130 {
+            let mut hasher = Md4::new();
+
+            for chunk in data.chunks(chunk_len) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(expected, hasher.finalize());
+        }
+    }
+
+    #[test]
+    fn ntlm_hashes() {
+        assert_eq!("31d6cfe0d16ae931b73c59d7e0c089c0", ntlm("").to_string());
+        assert_eq!(
+            "8846f7eaee8fb117ad06bdd830b7586c",
+            ntlm("password").to_string()
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use clap::Parser;
+use hasher::checksum_file::{escape, unescape};
+use md5::{Digest, Md5};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::num::NonZeroUsize;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+const STDIN_PATH: &str = "-";
+
+/// Print or check MD5 (128-bit) checksums, compatible with GNU `md5sum`
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct CliArgs {
+    /// Files to hash or checksum files to check, standard input is read when
+    /// none or `-` is given
+    files: Vec<String>,
+
+    /// Read MD5 sums from the files and check them
+    #[arg(short, long)]
+    check: bool,
+
+    /// Create a BSD-style checksum
+    #[arg(long, conflicts_with = "check")]
+    tag: bool,
+
+    /// Read in binary mode
+    #[arg(short, long, conflicts_with = "text")]
+    binary: bool,
+
+    /// Read in text mode (default)
+    #[arg(short, long)]
+    text: bool,
+
+    /// Don't print OK for each successfully verified file
+    #[arg(long, requires = "check")]
+    quiet: bool,
+
+    /// Don't output anything, status code shows success
+    #[arg(long, requires = "check")]
+    status: bool,
+
+    /// Don't fail or report status for missing files
+    #[arg(long, requires = "check")]
+    ignore_missing: bool,
+
+    /// Number of files hashed in parallel, defaults to the number of CPUs
+    #[arg(short, long)]
+    jobs: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ChecksumLine {
+    expected: Digest,
+    path: String,
+}
+
+fn hash_reader(mut reader: impl Read) -> io::Result<Digest> {
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn hash_path(path: &str) -> io::Result<Digest> {
+    match path {
+        STDIN_PATH => hash_reader(io::stdin().lock()),
+        _ => hash_reader(File::open(path)?),
+    }
+}
+
+/// Hashes the files on `jobs` worker threads, results keep the order of `paths`.
+fn hash_paths(paths: &[&str], jobs: usize) -> Vec<io::Result<Digest>> {
+    let next_index = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<io::Result<Digest>>>> =
+        paths.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(paths.len()) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+
+                *results[index].lock().unwrap() = Some(hash_path(path));
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().expect("Every file is hashed"))
+        .collect()
+}
+
+fn parse_checksum_line(line: &str) -> Option<ChecksumLine> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+
+    let (expected, path) = match line.strip_prefix("MD5 (") {
+        Some(rest) => {
+            let (path, expected) = rest.rsplit_once(") = ")?;
+            (expected, path)
+        }
+        None => {
+            let expected = line.get(..32)?;
+            let path = line[32..]
+                .strip_prefix("  ")
+                .or_else(|| line[32..].strip_prefix(" *"))?;
+            (expected, path)
+        }
+    };
+
+    let expected = expected.parse().ok()?;
+
+    if path.is_empty() {
+        return None;
+    }
+
+    let path = match escaped {
+        true => unescape(path)?,
+        false => path.to_string(),
+    };
+
+    Some(ChecksumLine { expected, path })
+}
+
+fn plural<'a>(count: usize, singular: &'a str, plural: &'a str) -> &'a str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+fn print_digests(args: &CliArgs, files: &[&str], jobs: usize) -> bool {
+    let mut succeeded = true;
+
+    for (path, result) in files.iter().zip(hash_paths(files, jobs)) {
+        let digest = match result {
+            Ok(digest) => digest,
+            Err(error) => {
+                eprintln!("md5: {}: {}", path, error);
+                succeeded = false;
+                continue;
+            }
+        };
+
+        println!("{}", format_line(&digest, path, args.tag, args.binary));
+    }
+
+    succeeded
+}
+
+/// Output line of `digest`, in the BSD format with `tag` and in the GNU one
+/// otherwise, where `binary` marks the file name with `*`.
+fn format_line(digest: &Digest, path: &str, tag: bool, binary: bool) -> String {
+    let (escaped, name) = escape(path);
+    let prefix = if escaped { "\\" } else { "" };
+
+    if tag {
+        format!("{}MD5 ({}) = {}", prefix, name, digest)
+    } else {
+        let mode = if binary { '*' } else { ' ' };
+        format!("{}{} {}{}", prefix, digest, mode, name)
+    }
+}
+
+fn check_file(args: &CliArgs, checksum_path: &str, jobs: usize) -> bool {
+    let reader: Box<dyn BufRead> = match checksum_path {
+        STDIN_PATH => Box::new(io::stdin().lock()),
+        _ => match File::open(checksum_path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(error) => {
+                eprintln!("md5: {}: {}", checksum_path, error);
+                return false;
+            }
+        },
+    };
+
+    let mut lines = Vec::new();
+    let mut improperly_formatted = 0usize;
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                eprintln!("md5: {}: {}", checksum_path, error);
+                return false;
+            }
+        };
+
+        match parse_checksum_line(&line) {
+            Some(checksum_line) => lines.push(checksum_line),
+            None => improperly_formatted += 1,
+        }
+    }
+
+    if lines.is_empty() {
+        eprintln!(
+            "md5: {}: no properly formatted MD5 checksum lines found",
+            checksum_path
+        );
+        return false;
+    }
+
+    let paths: Vec<&str> = lines.iter().map(|line| line.path.as_str()).collect();
+    let mut unreadable = 0usize;
+    let mut mismatched = 0usize;
+    let mut verified = 0usize;
+
+    for (line, result) in lines.iter().zip(hash_paths(&paths, jobs)) {
+        let (escaped, name) = escape(&line.path);
+        let prefix = if escaped { "\\" } else { "" };
+
+        let status = match result {
+            Err(error) if args.ignore_missing && error.kind() == io::ErrorKind::NotFound => {
+                continue;
+            }
+            Err(error) => {
+                if !args.status {
+                    eprintln!("md5: {}: {}", line.path, error);
+                }
+                unreadable += 1;
+                "FAILED open or read"
+            }
+            Ok(digest) if digest != line.expected => {
+                mismatched += 1;
+                "FAILED"
+            }
+            Ok(_) => {
+                verified += 1;
+                if args.quiet {
+                    continue;
+                }
+                "OK"
+            }
+        };
+
+        if !args.status {
+            println!("{}{}: {}", prefix, name, status);
+        }
+    }
+
+    if !args.status {
+        if improperly_formatted > 0 {
+            eprintln!(
+                "md5: WARNING: {} {} improperly formatted",
+                improperly_formatted,
+                plural(improperly_formatted, "line is", "lines are")
+            );
+        }
+
+        if unreadable > 0 {
+            eprintln!(
+                "md5: WARNING: {} listed {} could not be read",
+                unreadable,
+                plural(unreadable, "file", "files")
+            );
+        }
+
+        if mismatched > 0 {
+            eprintln!(
+                "md5: WARNING: {} computed {} did NOT match",
+                mismatched,
+                plural(mismatched, "checksum", "checksums")
+            );
+        }
+
+        if args.ignore_missing && verified + unreadable + mismatched == 0 {
+            eprintln!("md5: {}: no file was verified", checksum_path);
+        }
+    }
+
+    unreadable == 0 && mismatched == 0 && (!args.ignore_missing || verified > 0)
+}
+
+fn main() -> ExitCode {
+    let cli_args = CliArgs::parse();
+
+    let jobs = cli_args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+
+    let files: Vec<&str> = match cli_args.files.is_empty() {
+        true => vec![STDIN_PATH],
+        false => cli_args.files.iter().map(String::as_str).collect(),
+    };
+
+    let succeeded = match cli_args.check {
+        true => files
+            .iter()
+            .map(|path| check_file(&cli_args, path, jobs))
+            .collect::<Vec<bool>>()
+            .into_iter()
+            .all(|succeeded| succeeded),
+        false => print_digests(&cli_args, &files, jobs),
+    };
+
+    match succeeded {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC: &str = "900150983cd24fb0d6963f7d28e17f72";
+
+    #[test]
+    fn formats_gnu_and_bsd_lines() {
+        let digest = md5::digest("abc");
+
+        assert_eq!(
+            format!("{}  abc.txt", ABC),
+            format_line(&digest, "abc.txt", false, false)
+        );
+        assert_eq!(
+            format!("{} *abc.txt", ABC),
+            format_line(&digest, "abc.txt", false, true)
+        );
+        assert_eq!(
+            format!("MD5 (abc.txt) = {}", ABC),
+            format_line(&digest, "abc.txt", true, false)
+        );
+        assert_eq!(
+            format!("\\{}  a\\nb", ABC),
+            format_line(&digest, "a\nb", false, false)
+        );
+        assert_eq!(
+            format!("\\MD5 (a\\\\b) = {}", ABC),
+            format_line(&digest, "a\\b", true, false)
+        );
+    }
+
+    #[test]
+    fn parses_checksum_lines() {
+        let line = |path: &str| {
+            Some(ChecksumLine {
+                expected: md5::digest("abc"),
+                path: path.to_string(),
+            })
+        };
+
+        assert_eq!(
+            line("abc.txt"),
+            parse_checksum_line(&format!("{}  abc.txt", ABC))
+        );
+        assert_eq!(
+            line("abc.txt"),
+            parse_checksum_line(&format!("{} *abc.txt", ABC))
+        );
+        assert_eq!(
+            line("two  spaces"),
+            parse_checksum_line(&format!("{}  two  spaces", ABC))
+        );
+        assert_eq!(
+            line("abc.txt"),
+            parse_checksum_line(&format!("{}  abc.txt\r", ABC))
+        );
+        assert_eq!(
+            line("a (b) = c"),
+            parse_checksum_line(&format!("MD5 (a (b) = c) = {}", ABC))
+        );
+        assert_eq!(
+            line("a\nb\\c"),
+            parse_checksum_line(&format!("\\{}  a\\nb\\\\c", ABC))
+        );
+        assert_eq!(
+            line("a\nb"),
+            parse_checksum_line(&format!("\\MD5 (a\\nb) = {}", ABC))
+        );
+
+        for line in [
+            "",
+            "900150983cd24fb0d6963f7d28e17f72",
+            "900150983cd24fb0d6963f7d28e17f72  ",
+            "900150983cd24fb0d6963f7d28e17f72 abc.txt",
+            "900150983cd24fb0d6963f7d28e17f7g  abc.txt",
+            "MD5 (abc.txt) 900150983cd24fb0d6963f7d28e17f72",
+            "\\900150983cd24fb0d6963f7d28e17f72  bad\\escape",
+        ] {
+            assert_eq!(None, parse_checksum_line(line), "Line: {:?}", line);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+mod mask;
+mod rules;
+
+use clap::{ArgGroup, Parser};
+use mask::Mask;
+use md5::multi::digest_x8;
+use rules::Rule;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LANES: usize = 8;
+const WORDS_PER_CHUNK: usize = 1024;
+const CANDIDATES_PER_CHUNK: u64 = 1 << 16;
+
+/// Recover the plaintexts of MD5 hashes with dictionary and brute-force attacks
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+#[command(group(ArgGroup::new("attack").required(true).multiple(true).args(["wordlist", "mask"])))]
+struct CliArgs {
+    /// File with one hex encoded MD5 hash per line
+    hashes: String,
+
+    /// Dictionary of candidate words, one per line
+    #[arg(short, long)]
+    wordlist: Option<String>,
+
+    /// Hashcat-style rules applied to every word of the dictionary
+    #[arg(short, long, requires = "wordlist")]
+    rules: Option<String>,
+
+    /// Brute-force mask such as `?u?l?l?l?d?d`, run after the dictionary
+    #[arg(short, long)]
+    mask: Option<String>,
+
+    /// Custom charset `?1` of the mask
+    #[arg(short = '1', long)]
+    custom_charset1: Option<String>,
+
+    /// Custom charset `?2` of the mask
+    #[arg(short = '2', long)]
+    custom_charset2: Option<String>,
+
+    /// Custom charset `?3` of the mask
+    #[arg(short = '3', long)]
+    custom_charset3: Option<String>,
+
+    /// Custom charset `?4` of the mask
+    #[arg(short = '4', long)]
+    custom_charset4: Option<String>,
+
+    /// Number of worker threads, defaults to the number of CPUs
+    #[arg(short, long)]
+    threads: Option<NonZeroUsize>,
+}
+
+struct Cracker {
+    targets: HashSet<[u8; 16]>,
+    found: Mutex<HashMap<[u8; 16], Vec<u8>>>,
+    remaining: AtomicUsize,
+    hashed: AtomicU64,
+}
+
+/// Candidates waiting to be hashed 8 at a time.
+struct Batch<'a> {
+    cracker: &'a Cracker,
+    candidates: [Vec<u8>; LANES],
+    length: usize,
+    hashed: u64,
+}
+
+fn parse_hash(line: &str) -> Option<[u8; 16]> {
+    let line = line.trim();
+
+    if line.len() != 32 || !line.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut hash = [0u8; 16];
+    for (index, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&line[index * 2..index * 2 + 2], 16).ok()?;
+    }
+
+    Some(hash)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Plaintexts that are not printable ASCII are shown as `$HEX[...]`, like
+/// hashcat does.
+fn format_plaintext(plaintext: &[u8]) -> String {
+    match plaintext.iter().all(|byte| (b' '..=b'~').contains(byte)) {
+        true => String::from_utf8_lossy(plaintext).into_owned(),
+        false => format!("$HEX[{}]", to_hex(plaintext)),
+    }
+}
+
+fn format_rate(rate: f64) -> String {
+    const UNITS: [&str; 4] = ["", "k", "M", "G"];
+
+    let mut rate = rate;
+    let mut unit = 0;
+
+    while rate >= 1000.0 && unit + 1 < UNITS.len() {
+        rate /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.2} {}H/s", rate, UNITS[unit])
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+
+    match seconds < 60.0 {
+        true => format!("{:.2}s", seconds),
+        false => format!(
+            "{}m {:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        ),
+    }
+}
+
+impl Cracker {
+    fn new(targets: HashSet<[u8; 16]>) -> Self {
+        Cracker {
+            remaining: AtomicUsize::new(targets.len()),
+            targets,
+            found: Mutex::new(HashMap::new()),
+            hashed: AtomicU64::new(0),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.remaining.load(Ordering::Relaxed) == 0
+    }
+
+    fn report(&self, hash: [u8; 16], plaintext: &[u8]) {
+        let mut found = self.found.lock().unwrap();
+
+        if found.contains_key(&hash) {
+            return;
+        }
+
+        println!("{}:{}", to_hex(&hash), format_plaintext(plaintext));
+        found.insert(hash, plaintext.to_vec());
+        self.remaining.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn batch(&self) -> Batch<'_> {
+        Batch {
+            cracker: self,
+            candidates: Default::default(),
+            length: 0,
+            hashed: 0,
+        }
+    }
+
+    /// Tries every word of the dictionary with every rule.
+    fn run_dictionary(&self, words: &[&[u8]], rules: &[Rule], threads: usize) {
+        let next_chunk = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let mut batch = self.batch();
+
+                    while !self.is_done() {
+                        let start = next_chunk.fetch_add(WORDS_PER_CHUNK, Ordering::Relaxed);
+                        let Some(chunk) =
+                            words.get(start..(start + WORDS_PER_CHUNK).min(words.len()))
+                        else {
+                            break;
+                        };
+
+                        for word in chunk {
+                            for rule in rules {
+                                rule.apply(word, batch.next_slot());
+                                batch.commit();
+                            }
+                        }
+                    }
+
+                    batch.finish();
+                });
+            }
+        });
+    }
+
+    /// Tries every candidate of the mask.
+    fn run_mask(&self, mask: &Mask, keyspace: u64, threads: usize) {
+        let next_chunk = AtomicU64::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let mut batch = self.batch();
+
+                    while !self.is_done() {
+                        let start = next_chunk.fetch_add(CANDIDATES_PER_CHUNK, Ordering::Relaxed);
+                        if start >= keyspace {
+                            break;
+                        }
+
+                        let mut cursor = mask.cursor(start);
+                        for _ in start..(start + CANDIDATES_PER_CHUNK).min(keyspace) {
+                            let slot = batch.next_slot();
+                            slot.clear();
+                            slot.extend_from_slice(cursor.candidate());
+                            batch.commit();
+                            cursor.advance();
+                        }
+                    }
+
+                    batch.finish();
+                });
+            }
+        });
+    }
+}
+
+impl Batch<'_> {
+    /// The buffer of the next candidate, `commit` adds it to the batch.
+    fn next_slot(&mut self) -> &mut Vec<u8> {
+        &mut self.candidates[self.length]
+    }
+
+    fn commit(&mut self) {
+        self.length += 1;
+
+        if self.length == LANES {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let inputs: [&[u8]; LANES] = std::array::from_fn(|lane| self.candidates[lane].as_slice());
+        let digests = digest_x8(inputs);
+
+        for (lane, digest) in digests.iter().enumerate().take(self.length) {
+            if self.cracker.targets.contains(&digest.0) {
+                self.cracker.report(digest.0, &self.candidates[lane]);
+            }
+        }
+
+        self.hashed += self.length as u64;
+        self.length = 0;
+    }
+
+    fn finish(mut self) {
+        if self.length > 0 {
+            self.flush();
+        }
+
+        self.cracker
+            .hashed
+            .fetch_add(self.hashed, Ordering::Relaxed);
+    }
+}
+
+fn read_hashes(path: &str) -> Result<HashSet<[u8; 16]>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut hashes = HashSet::new();
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_hash(line) {
+            Some(hash) => {
+                hashes.insert(hash);
+            }
+            None => eprintln!(
+                "md5-crack: {}:{}: not an MD5 hash, skipped",
+                path,
+                index + 1
+            ),
+        }
+    }
+
+    match hashes.is_empty() {
+        true => Err(format!("{}: no MD5 hash found", path)),
+        false => Ok(hashes),
+    }
+}
+
+fn read_rules(path: Option<&str>) -> Result<Vec<Rule>, String> {
+    let Some(path) = path else {
+        return Ok(vec![Rule::identity()]);
+    };
+
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let rules = rules::parse_rules(&text)
+        .map_err(|(line, error)| format!("{}:{}: {}", path, line, error))?;
+
+    match rules.is_empty() {
+        true => Err(format!("{}: no rule found", path)),
+        false => Ok(rules),
+    }
+}
+
+fn run(args: &CliArgs, threads: usize) -> Result<(), String> {
+    let cracker = Cracker::new(read_hashes(&args.hashes)?);
+    let start = Instant::now();
+
+    let mask = args
+        .mask
+        .as_deref()
+        .map(|mask| {
+            let custom = [
+                args.custom_charset1.clone(),
+                args.custom_charset2.clone(),
+                args.custom_charset3.clone(),
+                args.custom_charset4.clone(),
+            ];
+
+            let mask = Mask::parse(mask, &custom).map_err(|error| format!("mask: {}", error))?;
+            let keyspace = mask
+                .keyspace()
+                .ok_or_else(|| "mask: the keyspace does not fit in 64 bits".to_string())?;
+
+            Ok::<_, String>((mask, keyspace))
+        })
+        .transpose()?;
+
+    if let Some(path) = &args.wordlist {
+        let rules = read_rules(args.rules.as_deref())?;
+        let content = fs::read(path).map_err(|error| format!("{}: {}", path, error))?;
+        let words: Vec<&[u8]> = content
+            .split(|&byte| byte == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        eprintln!(
+            "md5-crack: dictionary attack, {} words x {} rules",
+            words.len(),
+            rules.len()
+        );
+        cracker.run_dictionary(&words, &rules, threads);
+    }
+
+    if let Some((mask, keyspace)) = &mask {
+        if !cracker.is_done() {
+            eprintln!("md5-crack: mask attack, {} candidates", keyspace);
+            cracker.run_mask(mask, *keyspace, threads);
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let hashed = cracker.hashed.load(Ordering::Relaxed);
+
+    eprintln!(
+        "md5-crack: recovered {}/{} hashes, {} candidates in {} ({})",
+        cracker.found.lock().unwrap().len(),
+        cracker.targets.len(),
+        hashed,
+        format_duration(elapsed),
+        format_rate(hashed as f64 / elapsed.as_secs_f64().max(f64::EPSILON))
+    );
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli_args = CliArgs::parse();
+
+    let threads = cli_args
+        .threads
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+
+    match run(&cli_args, threads) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("md5-crack: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
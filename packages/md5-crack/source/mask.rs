@@ -0,0 +1,225 @@
+//! Brute-force masks in the syntax of hashcat.
+//!
+//! Every position of a mask is either a literal character or a charset:
+//! `?l` lowercase letters, `?u` uppercase letters, `?d` digits, `?s` printable
+//! specials including the space, `?a` all of them, `?1` to `?4` custom
+//! charsets and `??` for a literal question mark. `?u?l?l?l?d?d` covers
+//! `Pass12` and `Word99`.
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SPECIALS: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+#[derive(Debug, Clone)]
+pub struct Mask {
+    positions: Vec<Vec<u8>>,
+}
+
+/// Expands `?x` placeholders of a charset definition, custom charsets are
+/// only available in masks.
+fn expand(definition: &str, custom: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+    let mut charsets = Vec::new();
+    let mut bytes = definition.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'?' {
+            charsets.push(vec![byte]);
+            continue;
+        }
+
+        let name = bytes
+            .next()
+            .ok_or_else(|| format!("Dangling '?' in '{}'", definition))?;
+
+        let charset = match name {
+            b'l' => LOWERCASE.to_vec(),
+            b'u' => UPPERCASE.to_vec(),
+            b'd' => DIGITS.to_vec(),
+            b's' => SPECIALS.to_vec(),
+            b'a' => [LOWERCASE, UPPERCASE, DIGITS, SPECIALS].concat(),
+            b'?' => vec![b'?'],
+            b'1'..=b'4' => custom
+                .get((name - b'1') as usize)
+                .filter(|charset| !charset.is_empty())
+                .cloned()
+                .ok_or_else(|| format!("Custom charset ?{} is not defined", name as char))?,
+            _ => return Err(format!("Unknown charset ?{}", name as char)),
+        };
+
+        charsets.push(charset);
+    }
+
+    Ok(charsets)
+}
+
+impl Mask {
+    /// Parses `mask`, `custom[n]` is the definition of the charset `?n+1`.
+    pub fn parse(mask: &str, custom: &[Option<String>; 4]) -> Result<Self, String> {
+        let custom = custom
+            .iter()
+            .map(|definition| match definition {
+                Some(definition) => {
+                    let mut charset = expand(definition, &[])?.concat();
+                    charset.sort_unstable();
+                    charset.dedup();
+                    Ok(charset)
+                }
+                None => Ok(Vec::new()),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let positions = expand(mask, &custom)?;
+
+        if positions.is_empty() {
+            return Err("Empty mask".to_string());
+        }
+
+        Ok(Mask { positions })
+    }
+
+    /// Number of candidates, `None` when it does not fit in 64 bits.
+    pub fn keyspace(&self) -> Option<u64> {
+        self.positions.iter().try_fold(1u64, |product, charset| {
+            product.checked_mul(charset.len() as u64)
+        })
+    }
+
+    /// A cursor on the candidate number `index`, the first position changes
+    /// fastest.
+    pub fn cursor(&self, mut index: u64) -> Cursor<'_> {
+        let mut digits = Vec::with_capacity(self.positions.len());
+        let mut candidate = Vec::with_capacity(self.positions.len());
+
+        for charset in &self.positions {
+            let digit = (index % charset.len() as u64) as usize;
+            index /= charset.len() as u64;
+
+            digits.push(digit);
+            candidate.push(charset[digit]);
+        }
+
+        Cursor {
+            mask: self,
+            digits,
+            candidate,
+        }
+    }
+}
+
+pub struct Cursor<'a> {
+    mask: &'a Mask,
+    digits: Vec<usize>,
+    candidate: Vec<u8>,
+}
+
+impl Cursor<'_> {
+    pub fn candidate(&self) -> &[u8] {
+        &self.candidate
+    }
+
+    /// Moves to the next candidate, wrapping around after the last one.
+    pub fn advance(&mut self) {
+        for (position, charset) in self.mask.positions.iter().enumerate() {
+            let digit = &mut self.digits[position];
+            *digit += 1;
+
+            if *digit < charset.len() {
+                self.candidate[position] = charset[*digit];
+                return;
+            }
+
+            *digit = 0;
+            self.candidate[position] = charset[0];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_CUSTOM: [Option<String>; 4] = [None, None, None, None];
+
+    fn candidates(mask: &Mask, start: u64, count: usize) -> Vec<String> {
+        let mut cursor = mask.cursor(start);
+
+        (0..count)
+            .map(|_| {
+                let candidate = String::from_utf8(cursor.candidate().to_vec()).unwrap();
+                cursor.advance();
+                candidate
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keyspace_is_the_product_of_charsets() {
+        let keyspace = |mask: &str| Mask::parse(mask, &NO_CUSTOM).unwrap().keyspace();
+
+        assert_eq!(Some(26u64.pow(4) * 100), keyspace("?u?l?l?l?d?d"));
+        assert_eq!(Some(95), keyspace("?a"));
+        assert_eq!(Some(33), keyspace("?s"));
+        assert_eq!(Some(1), keyspace("abc??"));
+        assert_eq!(Some(95u64.pow(9)), keyspace("?a?a?a?a?a?a?a?a?a"));
+        assert_eq!(None, keyspace("?a?a?a?a?a?a?a?a?a?a"));
+    }
+
+    #[test]
+    fn first_position_changes_fastest() {
+        let mask = Mask::parse("?d?lx", &NO_CUSTOM).unwrap();
+
+        assert_eq!(vec!["0ax", "1ax", "2ax"], candidates(&mask, 0, 3));
+        assert_eq!(vec!["8ax", "9ax", "0bx"], candidates(&mask, 8, 3));
+        assert_eq!(candidates(&mask, 11, 1), candidates(&mask, 0, 12)[11..]);
+
+        // The last candidate wraps around to the first
+        assert_eq!(vec!["9zx", "0ax"], candidates(&mask, 259, 2));
+    }
+
+    #[test]
+    fn custom_charsets_are_sorted_and_deduplicated() {
+        let custom = [
+            Some("?dab?d".to_string()),
+            None,
+            None,
+            Some("?".to_string()),
+        ];
+        let mask = Mask::parse("?1?4", &custom);
+
+        assert_eq!(
+            Err("Dangling '?' in '?'".to_string()),
+            mask.map(|mask| mask.keyspace())
+        );
+
+        let custom = [Some("c?da".to_string()), None, None, None];
+        let mask = Mask::parse("??-?1", &custom).unwrap();
+
+        assert_eq!(Some(12), mask.keyspace());
+        assert_eq!(vec!["?-0", "?-1"], candidates(&mask, 0, 2));
+        assert_eq!(vec!["?-9", "?-a", "?-c"], candidates(&mask, 9, 3));
+    }
+
+    #[test]
+    fn rejects_malformed_masks() {
+        let test_cases: &[(&str, &str)] = &[
+            ("", "Empty mask"),
+            ("abc?", "Dangling '?' in 'abc?'"),
+            ("?x", "Unknown charset ?x"),
+            ("?2", "Custom charset ?2 is not defined"),
+        ];
+
+        for &(mask, expected) in test_cases {
+            assert_eq!(
+                Err(expected.to_string()),
+                Mask::parse(mask, &NO_CUSTOM).map(|mask| mask.keyspace())
+            );
+        }
+
+        let custom = [Some("?1".to_string()), None, None, None];
+        assert_eq!(
+            Err("Custom charset ?1 is not defined".to_string()),
+            Mask::parse("?1", &custom).map(|mask| mask.keyspace())
+        );
+    }
+}
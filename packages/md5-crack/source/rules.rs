@@ -0,0 +1,303 @@
+//! Word mangling rules in the syntax of hashcat.
+//!
+//! A rule is a sequence of functions applied left to right, whitespace
+//! between functions is ignored. Positions are a single character, `0-9`
+//! then `A-Z` for 10 to 35. Supported functions:
+//!
+//! | Rule  | Effect                                  |
+//! |-------|-----------------------------------------|
+//! | `:`   | keep the word as is                     |
+//! | `l`   | lowercase all letters                   |
+//! | `u`   | uppercase all letters                   |
+//! | `c`   | capitalize the first letter, lower rest |
+//! | `C`   | lowercase the first letter, upper rest  |
+//! | `t`   | toggle the case of all letters          |
+//! | `TN`  | toggle the case at position N           |
+//! | `r`   | reverse                                 |
+//! | `d`   | duplicate                               |
+//! | `f`   | append the reversed word                |
+//! | `{`   | rotate left                             |
+//! | `}`   | rotate right                            |
+//! | `$X`  | append character X                      |
+//! | `^X`  | prepend character X                     |
+//! | `[`   | delete the first character              |
+//! | `]`   | delete the last character               |
+//! | `DN`  | delete the character at position N      |
+//! | `sXY` | replace every X with Y, e.g. leetspeak  |
+//! | `@X`  | remove every X                          |
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Nothing,
+    Lowercase,
+    Uppercase,
+    Capitalize,
+    InvertCapitalize,
+    ToggleCase,
+    ToggleAt(usize),
+    Reverse,
+    Duplicate,
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(u8),
+    Prepend(u8),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Substitute(u8, u8),
+    Purge(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule(Vec<Function>);
+
+fn toggle_case(byte: &mut u8) {
+    if byte.is_ascii_alphabetic() {
+        *byte ^= 0x20;
+    }
+}
+
+fn position(char: u8) -> Option<usize> {
+    match char {
+        b'0'..=b'9' => Some((char - b'0') as usize),
+        b'A'..=b'Z' => Some((char - b'A') as usize + 10),
+        _ => None,
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let mut functions = Vec::new();
+        let mut bytes = rule.bytes();
+
+        while let Some(name) = bytes.next() {
+            let mut argument = |kind: &str| {
+                bytes
+                    .next()
+                    .ok_or_else(|| format!("'{}' expects {}", name as char, kind))
+            };
+
+            let function = match name {
+                b' ' | b'\t' => continue,
+                b':' => Function::Nothing,
+                b'l' => Function::Lowercase,
+                b'u' => Function::Uppercase,
+                b'c' => Function::Capitalize,
+                b'C' => Function::InvertCapitalize,
+                b't' => Function::ToggleCase,
+                b'r' => Function::Reverse,
+                b'd' => Function::Duplicate,
+                b'f' => Function::Reflect,
+                b'{' => Function::RotateLeft,
+                b'}' => Function::RotateRight,
+                b'[' => Function::DeleteFirst,
+                b']' => Function::DeleteLast,
+                b'$' => Function::Append(argument("a character")?),
+                b'^' => Function::Prepend(argument("a character")?),
+                b'@' => Function::Purge(argument("a character")?),
+                b's' => {
+                    Function::Substitute(argument("two characters")?, argument("two characters")?)
+                }
+                b'T' | b'D' => {
+                    let char = argument("a position")?;
+                    let position = position(char)
+                        .ok_or_else(|| format!("'{}' is not a position", char as char))?;
+
+                    match name {
+                        b'T' => Function::ToggleAt(position),
+                        _ => Function::DeleteAt(position),
+                    }
+                }
+                _ => return Err(format!("Unknown rule function '{}'", name as char)),
+            };
+
+            functions.push(function);
+        }
+
+        if functions.is_empty() {
+            return Err("Empty rule".to_string());
+        }
+
+        Ok(Rule(functions))
+    }
+}
+
+impl Rule {
+    /// The rule that passes words through unchanged.
+    pub fn identity() -> Self {
+        Rule(vec![Function::Nothing])
+    }
+
+    /// Writes the mangled `word` into `output`, replacing its content.
+    pub fn apply(&self, word: &[u8], output: &mut Vec<u8>) {
+        output.clear();
+        output.extend_from_slice(word);
+
+        for function in &self.0 {
+            match *function {
+                Function::Nothing => {}
+                Function::Lowercase => output.make_ascii_lowercase(),
+                Function::Uppercase => output.make_ascii_uppercase(),
+                Function::Capitalize => {
+                    output.make_ascii_lowercase();
+                    if let Some(first) = output.first_mut() {
+                        first.make_ascii_uppercase();
+                    }
+                }
+                Function::InvertCapitalize => {
+                    output.make_ascii_uppercase();
+                    if let Some(first) = output.first_mut() {
+                        first.make_ascii_lowercase();
+                    }
+                }
+                Function::ToggleCase => output.iter_mut().for_each(toggle_case),
+                Function::ToggleAt(position) => {
+                    if let Some(byte) = output.get_mut(position) {
+                        toggle_case(byte);
+                    }
+                }
+                Function::Reverse => output.reverse(),
+                Function::Duplicate => output.extend_from_within(..),
+                Function::Reflect => {
+                    let length = output.len();
+                    output.extend_from_within(..);
+                    output[length..].reverse();
+                }
+                Function::RotateLeft => {
+                    if !output.is_empty() {
+                        output.rotate_left(1);
+                    }
+                }
+                Function::RotateRight => {
+                    if !output.is_empty() {
+                        output.rotate_right(1);
+                    }
+                }
+                Function::Append(byte) => output.push(byte),
+                Function::Prepend(byte) => output.insert(0, byte),
+                Function::DeleteFirst => {
+                    if !output.is_empty() {
+                        output.remove(0);
+                    }
+                }
+                Function::DeleteLast => {
+                    output.pop();
+                }
+                Function::DeleteAt(position) => {
+                    if position < output.len() {
+                        output.remove(position);
+                    }
+                }
+                Function::Substitute(from, to) => output
+                    .iter_mut()
+                    .filter(|byte| **byte == from)
+                    .for_each(|byte| *byte = to),
+                Function::Purge(byte) => output.retain(|&other| other != byte),
+            }
+        }
+    }
+}
+
+/// Parses a rule file, one rule per line. Blank lines and lines starting
+/// with `#` are skipped, errors carry the 1-based line number.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, (usize, String)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| line.parse().map_err(|error| (index + 1, error)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mangle(rule: &str, word: &str) -> String {
+        let mut output = Vec::new();
+        rule.parse::<Rule>()
+            .unwrap()
+            .apply(word.as_bytes(), &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn hashcat_reference_outputs() {
+        // The examples of the hashcat rule reference, on "p@ssW0rd"
+        let test_cases: &[(&str, &str)] = &[
+            (":", "p@ssW0rd"),
+            ("l", "p@ssw0rd"),
+            ("u", "P@SSW0RD"),
+            ("c", "P@ssw0rd"),
+            ("C", "p@SSW0RD"),
+            ("t", "P@SSw0RD"),
+            ("T3", "p@sSW0rd"),
+            ("r", "dr0Wss@p"),
+            ("d", "p@ssW0rdp@ssW0rd"),
+            ("f", "p@ssW0rddr0Wss@p"),
+            ("{", "@ssW0rdp"),
+            ("}", "dp@ssW0r"),
+            ("$1", "p@ssW0rd1"),
+            ("^1", "1p@ssW0rd"),
+            ("[", "@ssW0rd"),
+            ("]", "p@ssW0r"),
+            ("D3", "p@sW0rd"),
+            ("ss$", "p@$$W0rd"),
+            ("@s", "p@W0rd"),
+        ];
+
+        for &(rule, expected) in test_cases {
+            assert_eq!(expected, mangle(rule, "p@ssW0rd"), "Failed rule: {}", rule);
+        }
+    }
+
+    #[test]
+    fn functions_chain_left_to_right() {
+        assert_eq!("Password1", mangle("c $1", "password"));
+        assert_eq!("p@ssword", mangle("sa@", "password"));
+        assert_eq!("P@55w0rd!", mangle("c sa@ ss5 so0 $!", "password"));
+        assert_eq!("$ 1", mangle("^$ $  $1", ""));
+        assert_eq!("", mangle("] [ D0 T0 { }", ""));
+    }
+
+    #[test]
+    fn positions_above_nine_are_letters() {
+        assert_eq!("abcdefghijKl", mangle("TA", "abcdefghijkl"));
+        assert_eq!("abcdefghijk", mangle("DB", "abcdefghijkl"));
+        assert_eq!("abc", mangle("TZ DZ", "abc"));
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let test_cases: &[(&str, &str)] = &[
+            ("$", "'$' expects a character"),
+            ("c ^", "'^' expects a character"),
+            ("@", "'@' expects a character"),
+            ("sa", "'s' expects two characters"),
+            ("T", "'T' expects a position"),
+            ("Da", "'a' is not a position"),
+            ("x", "Unknown rule function 'x'"),
+            ("  ", "Empty rule"),
+        ];
+
+        for &(rule, expected) in test_cases {
+            assert_eq!(Err(expected.to_string()), rule.parse::<Rule>());
+        }
+    }
+
+    #[test]
+    fn rule_files_skip_comments_and_report_lines() {
+        let rules = parse_rules("# comment\n:\n\nc $1\n  \nsa@\n").unwrap();
+        assert_eq!(3, rules.len());
+        assert_eq!(Rule::identity(), rules[0]);
+
+        assert_eq!(
+            Err((4, "Unknown rule function 'x'".to_string())),
+            parse_rules(":\n# x\n\nx\nu\n")
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! C ABI of the md5 crate, built as `libmd5_ffi` for the front end.
+//!
+//! Inputs are `(pointer, length)` pairs so they may contain zero bytes, the
+//! pointer may be null when the length is 0. Digests are written as 16 raw
+//! bytes into a buffer owned by the caller. Strings returned by [`md5`] and
+//! [`md5_hmac`] must be released with [`md5_free`].
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::slice;
+
+use md5::{HmacMd5, Md5};
+
+/// Status codes of [`md5_file`].
+pub const MD5_FILE_OK: i32 = 0;
+pub const MD5_FILE_CANCELLED: i32 = 1;
+/// The path is not valid UTF-8, on targets other than Unix.
+pub const MD5_FILE_INVALID_PATH: i32 = 2;
+/// The file could not be opened or read.
+pub const MD5_FILE_IO_ERROR: i32 = 3;
+
+const FILE_CHUNK_SIZE: usize = 1 << 20;
+
+/// Called by [`md5_file`] after every chunk with the number of bytes hashed
+/// so far and the file size, 0 when unknown. Returning `false` cancels.
+pub type Md5ProgressCallback = Option<extern "C" fn(processed: u64, total: u64) -> bool>;
+
+/// # Safety
+///
+/// `data` must be valid for reads of `len` bytes unless `len` is 0.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    match len {
+        0 => &[],
+        _ => {
+            assert!(!data.is_null());
+            unsafe { slice::from_raw_parts(data, len) }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `output` must be valid for writes of 16 bytes.
+unsafe fn write_digest(digest: md5::Digest, output: *mut u8) {
+    assert!(!output.is_null());
+    unsafe { output.copy_from_nonoverlapping(digest.0.as_ptr(), digest.0.len()) };
+}
+
+fn into_c_string(digest: md5::Digest) -> *mut c_char {
+    CString::new(digest.to_string())
+        .expect("Hex digits contain no NUL byte")
+        .into_raw()
+}
+
+/// Hashes `len` bytes at `data` and writes the digest to `output`.
+///
+/// # Safety
+///
+/// `data` must be valid for reads of `len` bytes unless `len` is 0 and
+/// `output` must be valid for writes of 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn md5_digest(data: *const u8, len: usize, output: *mut u8) {
+    unsafe { write_digest(md5::digest_bytes(bytes(data, len)), output) }
+}
+
+/// Computes the HMAC-MD5 of `message` under `key` and writes it to `output`.
+///
+/// # Safety
+///
+/// `key` and `message` must be valid for reads of `key_len` and
+/// `message_len` bytes unless their length is 0 and `output` must be valid
+/// for writes of 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac_digest(
+    key: *const u8,
+    key_len: usize,
+    message: *const u8,
+    message_len: usize,
+    output: *mut u8,
+) {
+    let (key, message) = unsafe { (bytes(key, key_len), bytes(message, message_len)) };
+    unsafe { write_digest(md5::hmac(key, message), output) }
+}
+
+/// Returns the hex digest of a NUL-terminated string, to be released with
+/// [`md5_free`].
+///
+/// # Safety
+///
+/// `raw_input` must point to a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn md5(raw_input: *const c_char) -> *mut c_char {
+    let input = unsafe {
+        assert!(!raw_input.is_null());
+        CStr::from_ptr(raw_input)
+    };
+
+    into_c_string(md5::digest_bytes(input.to_bytes()))
+}
+
+/// Returns the hex HMAC-MD5 of NUL-terminated strings, to be released with
+/// [`md5_free`].
+///
+/// # Safety
+///
+/// `raw_key` and `raw_message` must point to valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac(
+    raw_key: *const c_char,
+    raw_message: *const c_char,
+) -> *mut c_char {
+    let (key, message) = unsafe {
+        assert!(!raw_key.is_null() && !raw_message.is_null());
+        (CStr::from_ptr(raw_key), CStr::from_ptr(raw_message))
+    };
+
+    into_c_string(md5::hmac(key.to_bytes(), message.to_bytes()))
+}
+
+/// Releases a string returned by [`md5`] or [`md5_hmac`], null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or come from this library and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn md5_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Allocates an incremental hasher, released by [`md5_hasher_finalize`] or
+/// [`md5_hasher_free`].
+#[no_mangle]
+pub extern "C" fn md5_hasher_new() -> *mut Md5 {
+    Box::into_raw(Box::new(Md5::new()))
+}
+
+/// Feeds `len` bytes at `data` to the hasher.
+///
+/// # Safety
+///
+/// `hasher` must come from [`md5_hasher_new`] and not be released yet, `data`
+/// must be valid for reads of `len` bytes unless `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hasher_update(hasher: *mut Md5, data: *const u8, len: usize) {
+    let hasher = unsafe {
+        assert!(!hasher.is_null());
+        &mut *hasher
+    };
+
+    hasher.update(unsafe { bytes(data, len) });
+}
+
+/// Writes the digest to `output` and releases the hasher.
+///
+/// # Safety
+///
+/// `hasher` must come from [`md5_hasher_new`] and not be released yet,
+/// `output` must be valid for writes of 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hasher_finalize(hasher: *mut Md5, output: *mut u8) {
+    let hasher = unsafe {
+        assert!(!hasher.is_null());
+        Box::from_raw(hasher)
+    };
+
+    unsafe { write_digest(hasher.finalize(), output) }
+}
+
+/// Releases a hasher without computing its digest, null is ignored.
+///
+/// # Safety
+///
+/// `hasher` must be null or come from [`md5_hasher_new`] and not be released
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hasher_free(hasher: *mut Md5) {
+    if !hasher.is_null() {
+        drop(unsafe { Box::from_raw(hasher) });
+    }
+}
+
+/// Allocates an incremental HMAC-MD5 keyed with `key_len` bytes at `key`,
+/// released by [`md5_hmac_finalize`] or [`md5_hmac_free`].
+///
+/// # Safety
+///
+/// `key` must be valid for reads of `key_len` bytes unless `key_len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac_new(key: *const u8, key_len: usize) -> *mut HmacMd5 {
+    Box::into_raw(Box::new(HmacMd5::new(unsafe { bytes(key, key_len) })))
+}
+
+/// Feeds `len` bytes at `data` to the HMAC.
+///
+/// # Safety
+///
+/// `hmac` must come from [`md5_hmac_new`] and not be released yet, `data`
+/// must be valid for reads of `len` bytes unless `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac_update(hmac: *mut HmacMd5, data: *const u8, len: usize) {
+    let hmac = unsafe {
+        assert!(!hmac.is_null());
+        &mut *hmac
+    };
+
+    hmac.update(unsafe { bytes(data, len) });
+}
+
+/// Writes the tag to `output` and releases the HMAC.
+///
+/// # Safety
+///
+/// `hmac` must come from [`md5_hmac_new`] and not be released yet, `output`
+/// must be valid for writes of 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac_finalize(hmac: *mut HmacMd5, output: *mut u8) {
+    let hmac = unsafe {
+        assert!(!hmac.is_null());
+        Box::from_raw(hmac)
+    };
+
+    unsafe { write_digest(hmac.finalize(), output) }
+}
+
+/// Releases an HMAC without computing its tag, null is ignored.
+///
+/// # Safety
+///
+/// `hmac` must be null or come from [`md5_hmac_new`] and not be released yet.
+#[no_mangle]
+pub unsafe extern "C" fn md5_hmac_free(hmac: *mut HmacMd5) {
+    if !hmac.is_null() {
+        drop(unsafe { Box::from_raw(hmac) });
+    }
+}
+
+/// The bytes of `path` as they are on Unix, where any of them but NUL may
+/// appear in a path, and the path they encode in UTF-8 elsewhere.
+#[cfg(unix)]
+fn to_path(path: &CStr) -> Option<&Path> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    Some(Path::new(OsStr::from_bytes(path.to_bytes())))
+}
+
+#[cfg(not(unix))]
+fn to_path(path: &CStr) -> Option<&Path> {
+    path.to_str().ok().map(Path::new)
+}
+
+fn hash_file(path: &Path, callback: Md5ProgressCallback) -> io::Result<Option<md5::Digest>> {
+    let mut file = File::open(path)?;
+    let total = file.metadata().map_or(0, |metadata| metadata.len());
+
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; FILE_CHUNK_SIZE];
+    let mut processed = 0u64;
+
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => return Ok(Some(hasher.finalize())),
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+
+        hasher.update(&buffer[..read]);
+        processed += read as u64;
+
+        if callback.is_some_and(|callback| !callback(processed, total)) {
+            return Ok(None);
+        }
+    }
+}
+
+/// Streams the file at the NUL-terminated `path` through MD5 in 1 MiB chunks,
+/// reporting progress to `callback` when it is not null, and writes the
+/// digest to `output` on success. Returns one of the `MD5_FILE_*` codes.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated string and `output` must be
+/// valid for writes of 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn md5_file(
+    path: *const c_char,
+    callback: Md5ProgressCallback,
+    output: *mut u8,
+) -> i32 {
+    let path = unsafe {
+        assert!(!path.is_null());
+        CStr::from_ptr(path)
+    };
+
+    let Some(path) = to_path(path) else {
+        return MD5_FILE_INVALID_PATH;
+    };
+
+    match hash_file(path, callback) {
+        Ok(Some(digest)) => {
+            unsafe { write_digest(digest, output) };
+            MD5_FILE_OK
+        }
+        Ok(None) => MD5_FILE_CANCELLED,
+        Err(_) => MD5_FILE_IO_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md5::{digest_bytes, hmac};
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    #[test]
+    fn raw_inputs_keep_zero_bytes_and_invalid_utf8() {
+        let data = b"abc\0def\xff\xfe";
+        let mut output = [0u8; 16];
+
+        unsafe { md5_digest(data.as_ptr(), data.len(), output.as_mut_ptr()) };
+        assert_eq!(digest_bytes(data).0, output);
+
+        unsafe { md5_digest(std::ptr::null(), 0, output.as_mut_ptr()) };
+        assert_eq!(digest_bytes(b"").0, output);
+
+        let key = b"k\0y";
+        unsafe {
+            md5_hmac_digest(
+                key.as_ptr(),
+                key.len(),
+                data.as_ptr(),
+                data.len(),
+                output.as_mut_ptr(),
+            )
+        };
+        assert_eq!(hmac(key, data).0, output);
+    }
+
+    #[test]
+    fn strings_are_hex_digests() {
+        let input = CString::new(b"\xffabc".to_vec()).unwrap();
+
+        unsafe {
+            let digest = md5(input.as_ptr());
+            assert_eq!(
+                digest_bytes(b"\xffabc").to_string().as_bytes(),
+                CStr::from_ptr(digest).to_bytes()
+            );
+            md5_free(digest);
+
+            let tag = md5_hmac(input.as_ptr(), input.as_ptr());
+            assert_eq!(
+                hmac(b"\xffabc", b"\xffabc").to_string().as_bytes(),
+                CStr::from_ptr(tag).to_bytes()
+            );
+            md5_free(tag);
+
+            md5_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn handles_hash_incrementally() {
+        let data: Vec<u8> = (0..300).map(|i| (i * 13) as u8).collect();
+        let mut output = [0u8; 16];
+
+        unsafe {
+            let hasher = md5_hasher_new();
+            let hmac_handle = md5_hmac_new(b"key".as_ptr(), 3);
+
+            for chunk in data.chunks(7) {
+                md5_hasher_update(hasher, chunk.as_ptr(), chunk.len());
+                md5_hmac_update(hmac_handle, chunk.as_ptr(), chunk.len());
+            }
+
+            md5_hasher_finalize(hasher, output.as_mut_ptr());
+            assert_eq!(digest_bytes(&data).0, output);
+
+            md5_hmac_finalize(hmac_handle, output.as_mut_ptr());
+            assert_eq!(hmac(b"key", &data).0, output);
+
+            md5_hasher_free(md5_hasher_new());
+            md5_hmac_free(md5_hmac_new(std::ptr::null(), 0));
+        }
+    }
+
+    #[test]
+    fn files_are_streamed_with_progress_and_cancellation() {
+        static LAST_PROCESSED: AtomicU64 = AtomicU64::new(0);
+        static TOTAL: AtomicU64 = AtomicU64::new(0);
+        static IN_ORDER: AtomicBool = AtomicBool::new(true);
+
+        // A panic can't unwind out of an `extern "C"` callback, the values are
+        // checked once `md5_file` returns.
+        extern "C" fn record(processed: u64, total: u64) -> bool {
+            let previous = LAST_PROCESSED.swap(processed, Ordering::SeqCst);
+            if processed <= previous || processed > total {
+                IN_ORDER.store(false, Ordering::SeqCst);
+            }
+
+            TOTAL.store(total, Ordering::SeqCst);
+            true
+        }
+
+        extern "C" fn cancel(_: u64, _: u64) -> bool {
+            false
+        }
+
+        let data: Vec<u8> = (0..FILE_CHUNK_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let path = std::env::temp_dir().join(format!("md5-ffi-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let raw_path = CString::new(path.to_str().unwrap()).unwrap();
+        let mut output = [0u8; 16];
+
+        unsafe {
+            let status = md5_file(raw_path.as_ptr(), Some(record), output.as_mut_ptr());
+            assert_eq!(MD5_FILE_OK, status);
+            assert_eq!(digest_bytes(&data).0, output);
+            assert!(IN_ORDER.load(Ordering::SeqCst));
+            assert_eq!(data.len() as u64, LAST_PROCESSED.load(Ordering::SeqCst));
+            assert_eq!(data.len() as u64, TOTAL.load(Ordering::SeqCst));
+
+            output = [0u8; 16];
+            let status = md5_file(raw_path.as_ptr(), Some(cancel), output.as_mut_ptr());
+            assert_eq!(MD5_FILE_CANCELLED, status);
+            assert_eq!([0u8; 16], output);
+
+            let status = md5_file(raw_path.as_ptr(), None, output.as_mut_ptr());
+            assert_eq!(MD5_FILE_OK, status);
+            assert_eq!(digest_bytes(&data).0, output);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+
+        let missing = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let status = md5_file(missing.as_ptr(), None, output.as_mut_ptr());
+            assert_eq!(MD5_FILE_IO_ERROR, status);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_paths_may_be_invalid_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = [
+            b"md5-ffi-\xff-".as_slice(),
+            std::process::id().to_string().as_bytes(),
+        ]
+        .concat();
+        let path = std::env::temp_dir().join(std::ffi::OsStr::from_bytes(&name));
+        std::fs::write(&path, b"abc").unwrap();
+
+        let raw_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let mut output = [0u8; 16];
+        let status = unsafe { md5_file(raw_path.as_ptr(), None, output.as_mut_ptr()) };
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(MD5_FILE_OK, status);
+        assert_eq!(digest_bytes(b"abc").0, output);
+    }
+
+    #[cfg(not(unix))]
+    #[test]
+    fn other_paths_must_be_utf8() {
+        let invalid = CString::new(b"\xff".to_vec()).unwrap();
+        let mut output = [0u8; 16];
+        let status = unsafe { md5_file(invalid.as_ptr(), None, output.as_mut_ptr()) };
+
+        assert_eq!(MD5_FILE_INVALID_PATH, status);
+    }
+}
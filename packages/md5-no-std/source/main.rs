@@ -0,0 +1,77 @@
+//! Checks that md5 builds and runs without `std` and without an allocator.
+//!
+//! The binary is `no_std` and `no_main`: it only links against the C runtime
+//! for `main`, so pulling `std` into the md5 crate fails the build with a
+//! duplicate `panic_impl` lang item. The exit status is the number of failed
+//! checks.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+
+use md5::{Digest, HmacMd5, Md5};
+
+#[link(name = "c")]
+extern "C" {
+    fn abort() -> !;
+}
+
+#[panic_handler]
+fn panic(_: &PanicInfo) -> ! {
+    unsafe { abort() }
+}
+
+/// Referenced by the precompiled `core`, never called with `panic = "abort"`.
+#[no_mangle]
+extern "C" fn rust_eh_personality() {}
+
+const RFC_1321_TEST_SUITE: &[(&str, &str)] = &[
+    ("", "d41d8cd98f00b204e9800998ecf8427e"),
+    ("a", "0cc175b9c0f1b6a831c399e269772661"),
+    ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+    ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+    (
+        "abcdefghijklmnopqrstuvwxyz",
+        "c3fcd3d76192e4007dfb496cca67e13b",
+    ),
+    (
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        "d174ab98d277d9f5a5611c2c9f419d9f",
+    ),
+    (
+        "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        "57edf4a22be3c955ac49da2e2107b67a",
+    ),
+];
+
+fn expected(hex: &str) -> Digest {
+    hex.parse().expect("Test vectors are valid hex")
+}
+
+#[no_mangle]
+pub extern "C" fn main(_argc: i32, _argv: *const *const u8) -> i32 {
+    let mut failures = 0;
+
+    for &(input, hex) in RFC_1321_TEST_SUITE {
+        failures += (md5::digest(input) != expected(hex)) as i32;
+
+        let mut hasher = Md5::new();
+        for chunk in input.as_bytes().chunks(3) {
+            hasher.update(chunk);
+        }
+        failures += (hasher.finalize() != expected(hex)) as i32;
+    }
+
+    let tag = md5::hmac(b"Jefe", b"what do ya want for nothing?");
+    failures += (tag != expected("750c783e6ab0b503eaa86e310a5db738")) as i32;
+
+    let mut verifier = HmacMd5::new(b"Jefe");
+    verifier.update(b"what do ya want for nothing?");
+    failures += !verifier.verify(&tag.0) as i32;
+
+    let base64 = Digest::from_base64("1B2M2Y8AsgTpgAmY7PhCfg==");
+    failures += (base64 != Ok(md5::digest(""))) as i32;
+
+    failures
+}
@@ -0,0 +1,12 @@
+//! Runs the `no_std` binary, so `cargo test` covers md5 without `std`.
+
+use std::process::Command;
+
+#[test]
+fn vectors_pass_without_std() {
+    let status = Command::new(env!("CARGO_BIN_EXE_md5-no-std"))
+        .status()
+        .expect("The no_std binary runs");
+
+    assert_eq!(Some(0), status.code(), "Failed checks: {:?}", status.code());
+}
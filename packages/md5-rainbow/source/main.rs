@@ -0,0 +1,203 @@
+use clap::{Args, Parser, Subcommand};
+use md5::rainbow::{self, Parameters, Reduction, Space, Table};
+use md5::Digest;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Instant;
+
+/// Generate MD5 rainbow tables and look hashes up in them
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct CliArgs {
+    #[command(subcommand)]
+    operation: Operation,
+}
+
+#[derive(Subcommand, Debug)]
+enum Operation {
+    Generate(GenerateArgs),
+    Lookup(LookupArgs),
+}
+
+#[derive(Args, Debug)]
+struct GenerateArgs {
+    /// Path of the table file to create
+    output: String,
+
+    /// Characters of the plaintexts
+    #[arg(short, long, default_value = "abcdefghijklmnopqrstuvwxyz0123456789")]
+    charset: String,
+
+    /// Minimum plaintext length
+    #[arg(long, default_value_t = 1)]
+    min_length: usize,
+
+    /// Maximum plaintext length
+    #[arg(long, default_value_t = 6)]
+    max_length: usize,
+
+    /// Number of hash and reduction steps of every chain
+    #[arg(short = 'l', long, default_value_t = 2000)]
+    chain_length: u32,
+
+    /// Number of chains to compute
+    #[arg(short = 'n', long, default_value_t = 1_000_000)]
+    chains: u64,
+
+    /// Reduction function, `modulo` or `mixed`
+    #[arg(short, long, default_value = "mixed")]
+    reduction: Reduction,
+
+    /// Index of the table, tables of the same space need different indices
+    #[arg(short = 'i', long, default_value_t = 0)]
+    table_index: u32,
+
+    /// Number of worker threads, defaults to the number of CPUs
+    #[arg(short, long)]
+    threads: Option<NonZeroUsize>,
+}
+
+#[derive(Args, Debug)]
+struct LookupArgs {
+    /// Hex encoded MD5 hashes to invert
+    hashes: Vec<String>,
+
+    /// Table files, every one is searched until the plaintext is found
+    #[arg(short, long, required = true)]
+    table: Vec<String>,
+
+    /// File with one hex encoded MD5 hash per line
+    #[arg(short, long)]
+    file: Option<String>,
+}
+
+fn generate(args: GenerateArgs) -> Result<(), String> {
+    let threads = args
+        .threads
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+
+    let space = Space::new(args.charset.as_bytes(), args.min_length, args.max_length)
+        .map_err(|error| error.to_string())?;
+    let parameters = Parameters {
+        space,
+        chain_length: args.chain_length,
+        reduction: args.reduction,
+        table_index: args.table_index,
+    };
+
+    let start = Instant::now();
+    let table =
+        Table::generate(parameters, args.chains, threads).map_err(|error| error.to_string())?;
+
+    let file = File::create(&args.output).map_err(|error| format!("{}: {}", args.output, error))?;
+    let mut writer = BufWriter::new(file);
+    table
+        .write_to(&mut writer)
+        .map_err(|error| format!("{}: {}", args.output, error))?;
+    writer
+        .flush()
+        .map_err(|error| format!("{}: {}", args.output, error))?;
+
+    eprintln!(
+        "md5-rainbow: {}: {} chains, {} stored after removing merges, in {:.2}s",
+        args.output,
+        table.generated_chains(),
+        table.stored_chains(),
+        start.elapsed().as_secs_f64()
+    );
+    eprintln!(
+        "md5-rainbow: {}: space of {} plaintexts, success probability {:.2}%",
+        args.output,
+        table.parameters().space.size(),
+        table.success_probability() * 100.0
+    );
+
+    Ok(())
+}
+
+fn lookup(args: LookupArgs) -> Result<bool, String> {
+    let mut hashes = args.hashes;
+
+    if let Some(path) = &args.file {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        hashes.extend(
+            text.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    let digests = hashes
+        .iter()
+        .map(|hash| {
+            hash.trim()
+                .parse()
+                .map_err(|_| format!("{}: not an MD5 hash", hash.trim()))
+        })
+        .collect::<Result<Vec<Digest>, String>>()?;
+
+    let mut tables = Vec::new();
+    for path in &args.table {
+        let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+        let table = Table::read_from(BufReader::new(file))
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        eprintln!(
+            "md5-rainbow: {}: {} chains of {}, success probability {:.2}%",
+            path,
+            table.stored_chains(),
+            table.parameters().chain_length,
+            table.success_probability() * 100.0
+        );
+        tables.push(table);
+    }
+
+    eprintln!(
+        "md5-rainbow: combined success probability {:.2}%",
+        rainbow::combined_success_probability(&tables) * 100.0
+    );
+
+    let start = Instant::now();
+    let mut recovered = 0usize;
+
+    for digest in &digests {
+        match tables.iter().find_map(|table| table.lookup(digest)) {
+            Some(plaintext) => {
+                println!("{}:{}", digest, String::from_utf8_lossy(&plaintext));
+                recovered += 1;
+            }
+            None => println!("{}: not found", digest),
+        }
+    }
+
+    eprintln!(
+        "md5-rainbow: recovered {}/{} hashes in {:.2}s",
+        recovered,
+        digests.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(recovered == digests.len())
+}
+
+fn main() -> ExitCode {
+    let cli_args = CliArgs::parse();
+
+    let result = match cli_args.operation {
+        Operation::Generate(args) => generate(args).map(|()| true),
+        Operation::Lookup(args) => lookup(args),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("md5-rainbow: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
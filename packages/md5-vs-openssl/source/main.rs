@@ -0,0 +1,84 @@
+use clap::Parser;
+use openssl::hash::{hash, MessageDigest};
+use std::hint::black_box;
+use std::time::Instant;
+
+#[macro_use]
+extern crate prettytable;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct CliArgs {
+    /// Data file path, a random 16 MiB buffer is hashed when omitted
+    #[arg(short, long)]
+    data: Option<String>,
+
+    /// Number of iterations
+    #[arg(short, long, default_value_t = 100)]
+    iterations: usize,
+}
+
+fn measure_execution_time<F>(mut func: F) -> u128
+where
+    F: FnMut(),
+{
+    let start = Instant::now();
+    func();
+    Instant::now().duration_since(start).as_micros()
+}
+
+fn throughput(bytes: usize, iterations: usize, micros: u128) -> String {
+    let megabytes = (bytes * iterations) as f64 / (1024.0 * 1024.0);
+    let seconds = micros.max(1) as f64 / 1_000_000.0;
+
+    format!("{:.2}", megabytes / seconds)
+}
+
+fn main() {
+    let cli_args = CliArgs::parse();
+    let data = match cli_args.data {
+        Some(path) => std::fs::read(path).expect("Cannot read data from file"),
+        None => {
+            let mut buffer = vec![0u8; 16 * 1024 * 1024];
+            openssl::rand::rand_bytes(&mut buffer).expect("Cannot generate random data");
+            buffer
+        }
+    };
+
+    assert_eq!(
+        md5::digest_bytes(&data).0.as_slice(),
+        &*hash(MessageDigest::md5(), &data).expect("OpenSSL hashing failed"),
+        "Digests of both implementations must match"
+    );
+
+    let mut md5_time = 0u128;
+    let mut openssl_time = 0u128;
+
+    for _ in 0..cli_args.iterations {
+        // `black_box` keeps the optimizer from dropping the unused digests.
+        md5_time += measure_execution_time(|| {
+            black_box(md5::digest_bytes(black_box(&data)));
+        });
+
+        openssl_time += measure_execution_time(|| {
+            black_box(
+                hash(MessageDigest::md5(), black_box(&data)).expect("OpenSSL hashing failed"),
+            );
+        });
+    }
+
+    table!(
+        ["Implementation", "Total Time (us)", "Throughput (MiB/s)"],
+        [
+            "md5",
+            md5_time,
+            throughput(data.len(), cli_args.iterations, md5_time)
+        ],
+        [
+            "OpenSSL",
+            openssl_time,
+            throughput(data.len(), cli_args.iterations, openssl_time)
+        ]
+    )
+    .printstd();
+}
@@ -0,0 +1,584 @@
+//! Identical-prefix MD5 collisions.
+//!
+//! The prefix is zero-padded to whole blocks and followed by two blocks that
+//! differ only in `m4`, `m11` and `m14`, as in the differential attack of
+//! Wang and Yu. The first block leaves the chaining values with the
+//! difference `(2^31, 2^31 + 2^25, 2^31 + 2^25, 2^31 + 2^25)` and the second
+//! one cancels it, so both messages collide, and keep colliding under any
+//! common suffix.
+//!
+//! The first block is searched with the bit conditions and tunnels of
+//! Stevens' fastcoll. The second block follows Wang's original path, whose
+//! round one conditions are tabulated below, with tunnels on Q4 and Q9 that
+//! keep the first eight steps of round two intact.
+//!
+//! Q is indexed like in the literature: `Q[-3..=0]` is the chaining value
+//! `(a, d, c, b)` and `Q[t + 1]` is the register written by step `t`, stored
+//! at `Q_OFFSET + t + 1`.
+
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{compress_words, f, g, h, i, Md5, K};
+
+const Q_OFFSET: usize = 3;
+
+const ROTATIONS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// Message differences of the first and the second block.
+const BLOCK0_DIFFERENCE: [u32; 16] = message_difference(1 << 15);
+const BLOCK1_DIFFERENCE: [u32; 16] = message_difference(0xffff8000);
+
+/// Chaining value difference left by the first block.
+const NEAR_COLLISION: [u32; 4] = [
+    1 << 31,
+    (1 << 31) + (1 << 25),
+    (1 << 31) + (1 << 25),
+    (1 << 31) + (1 << 25),
+];
+
+/// Conditions on Q1..=Q16 of the second block as (bits set to zero, bits set
+/// to one, bits equal to the previous Q). Bits of `Q5`/`Q6` and `Q10`/`Q11`
+/// are pinned so that flipping the tunnel bits of Q4 and Q9 only changes
+/// message words used late in round two.
+const BLOCK1_CONDITIONS: [[u32; 3]; 16] = [
+    [0x0a000820, 0x84200000, 0x00000000],
+    [0x02200026, 0x8c000800, 0x701f10c0],
+    [0x40201080, 0xbe1f0966, 0x00000018],
+    [0x443b19ee, 0xba040010, 0x00000601],
+    [0xb5d0f1af, 0x482f0e50, 0x00000000],
+    [0x9a1113a9, 0x05e2ec56, 0x00000000],
+    [0x083201c0, 0x96011e01, 0x01808000],
+    [0x1b810001, 0x843283c0, 0x00000002],
+    [0x03828202, 0x9c0101c1, 0x00001000],
+    [0x6074101f, 0x878383c0, 0x00000000],
+    [0x00021000, 0xe07583df, 0x00086000],
+    [0x0007e000, 0x80081080, 0x7f000000],
+    [0xc0000080, 0x3f0fe008, 0x00000000],
+    [0xbf040000, 0x400be088, 0x00000000],
+    [0x82008008, 0x7d000000, 0x00000000],
+    [0x80000000, 0x20000000, 0x00000000],
+];
+
+/// XOR difference of Q1..=Q60 between the two messages along the second
+/// block path, the last four steps only have to cancel the chaining values.
+const BLOCK1_PATH: [u32; 60] = [
+    0x82000000, 0x82000020, 0xfe3f18e0, 0x8600003e, 0x80001fc1, 0x80330000, 0x980003c0, 0x87838000,
+    0x800003c3, 0x80001000, 0x80000000, 0x800fe080, 0xff000000, 0x80000000, 0x80008008, 0xa0000000,
+    0x80000000, 0x80000000, 0x80020000, 0x80000000, 0x80000000, 0x80000000, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000,
+    0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000,
+    0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000, 0x80000000,
+    0x80000000, 0x80000000, 0x80000000,
+];
+
+const BLOCK1_Q4_TUNNEL: u32 = 0x01c0e000;
+const BLOCK1_Q9_TUNNEL: u32 = 0x6074001c;
+
+/// A pair of distinct messages sharing the (padded) prefix and the MD5 digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// The prefix, zero-padded to a multiple of 64 bytes.
+    pub prefix: Vec<u8>,
+    /// The differing 128-byte tails of the first and the second message.
+    pub blocks: [[u8; 128]; 2],
+}
+
+impl Collision {
+    /// Both colliding messages, `prefix || blocks[0]` and `prefix || blocks[1]`.
+    pub fn messages(&self) -> (Vec<u8>, Vec<u8>) {
+        (
+            [&self.prefix[..], &self.blocks[0]].concat(),
+            [&self.prefix[..], &self.blocks[1]].concat(),
+        )
+    }
+}
+
+/// Finds a collision for `prefix`, seeding the search from the clock.
+pub fn find(prefix: &[u8]) -> Collision {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+
+    find_with_seed(prefix, seed)
+}
+
+/// Finds a collision for `prefix`, the same seed always yields the same blocks.
+pub fn find_with_seed(prefix: &[u8], seed: u64) -> Collision {
+    let mut prefix = prefix.to_vec();
+    prefix.resize(prefix.len().next_multiple_of(64), 0);
+
+    let mut hasher = Md5::new();
+    hasher.update(&prefix);
+
+    let mut rng = Xorshift::new(seed);
+    let mut ihv = hasher.state;
+
+    let block0 = find_block0(ihv, &mut rng);
+    compress_words(&mut ihv, &block0);
+    let block1 = find_block1(ihv, &mut rng);
+
+    let mut blocks = [[0u8; 128]; 2];
+
+    for (index, word) in block0.iter().chain(&block1).enumerate() {
+        let difference = match index < 16 {
+            true => BLOCK0_DIFFERENCE[index],
+            false => BLOCK1_DIFFERENCE[index - 16],
+        };
+
+        blocks[0][index * 4..index * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        blocks[1][index * 4..index * 4 + 4]
+            .copy_from_slice(&word.wrapping_add(difference).to_le_bytes());
+    }
+
+    Collision { prefix, blocks }
+}
+
+const fn message_difference(m11: u32) -> [u32; 16] {
+    let mut difference = [0u32; 16];
+    difference[4] = 1 << 31;
+    difference[11] = m11;
+    difference[14] = 1 << 31;
+    difference
+}
+
+/// Marsaglia's xorshift generator, as used by fastcoll.
+struct Xorshift(u32, u32);
+
+impl Xorshift {
+    fn new(seed: u64) -> Self {
+        Xorshift(seed as u32, (seed >> 32) as u32 | 1)
+    }
+
+    fn next(&mut self) -> u32 {
+        let t = self.0 ^ (self.0 << 10);
+        self.0 = self.1;
+        self.1 = self.1 ^ (self.1 >> 10) ^ t ^ (t >> 13);
+        self.0
+    }
+}
+
+/// All values whose set bits are a subset of `mask`, starting with zero.
+fn subsets(mask: u32) -> impl Iterator<Item = u32> {
+    let mut next = Some(0u32);
+
+    std::iter::from_fn(move || {
+        let current = next?;
+        let following = current.wrapping_sub(mask) & mask;
+        next = (following != 0).then_some(following);
+        Some(current)
+    })
+}
+
+fn initial_q(ihv: [u32; 4]) -> [u32; 68] {
+    let mut q = [0u32; 68];
+    q[..4].copy_from_slice(&[ihv[0], ihv[3], ihv[2], ihv[1]]);
+    q
+}
+
+fn with_difference(block: &[u32; 16], difference: &[u32; 16]) -> [u32; 16] {
+    std::array::from_fn(|index| block[index].wrapping_add(difference[index]))
+}
+
+#[inline(always)]
+fn word_index(t: usize) -> usize {
+    match t / 16 {
+        0 => t,
+        1 => (5 * t + 1) % 16,
+        2 => (3 * t + 5) % 16,
+        _ => (7 * t) % 16,
+    }
+}
+
+/// Sum of step `t` before the rotation, given the message word it consumes.
+#[inline(always)]
+fn step_sum(q: &[u32; 68], t: usize, word: u32) -> u32 {
+    let index = Q_OFFSET + t;
+    let (x, y, z) = (q[index], q[index - 1], q[index - 2]);
+    let function = match t / 16 {
+        0 => f(x, y, z),
+        1 => g(x, y, z),
+        2 => h(x, y, z),
+        _ => i(x, y, z),
+    };
+
+    function
+        .wrapping_add(q[index - 3])
+        .wrapping_add(K[t])
+        .wrapping_add(word)
+}
+
+#[inline(always)]
+fn forward_step(q: &mut [u32; 68], t: usize, word: u32) {
+    let index = Q_OFFSET + t;
+    let sum = step_sum(q, t, word).rotate_left(ROTATIONS[t / 16 * 4 + t % 4]);
+    q[index + 1] = q[index].wrapping_add(sum);
+}
+
+/// Message word of round one step `t` that turns `Q[t]` into `Q[t + 1]`.
+#[inline(always)]
+fn reverse_step(q: &[u32; 68], t: usize) -> u32 {
+    let index = Q_OFFSET + t;
+
+    q[index + 1]
+        .wrapping_sub(q[index])
+        .rotate_right(ROTATIONS[t % 4])
+        .wrapping_sub(step_sum(q, t, 0))
+}
+
+fn is_near_collision(ihv: [u32; 4], block: &[u32; 16]) -> bool {
+    let mut first = ihv;
+    let mut second = ihv;
+    compress_words(&mut first, block);
+    compress_words(&mut second, &with_difference(block, &BLOCK0_DIFFERENCE));
+
+    (0..4).all(|index| second[index].wrapping_sub(first[index]) == NEAR_COLLISION[index])
+}
+
+/// Chaining value conditions the second block path relies on.
+fn suits_block1(ihv: [u32; 4]) -> bool {
+    ihv[1] & 0x06000020 == 0
+        && ihv[2] & 0x06000000 == 0x02000000
+        && ihv[3] & 0x02000000 == 0
+        && (ihv[1] ^ ihv[2]) & 0x80000000 == 0
+        && (ihv[1] ^ ihv[3]) & 0x80000000 == 0
+}
+
+/// Steps 24 to 63 of the first block with fastcoll's early-abort checks on
+/// the carry of step 34 and on the most significant bits of rounds three
+/// and four.
+fn block0_tail(q: &mut [u32; 68], block: &[u32; 16]) -> bool {
+    for t in 24..64 {
+        if t == 34 && step_sum(q, t, block[word_index(t)]) & (1 << 15) != 0 {
+            return false;
+        }
+
+        forward_step(q, t, block[word_index(t)]);
+
+        let index = Q_OFFSET + t;
+        let msb_differs = (q[index + 1] ^ q[index - 1]) >> 31 != 0;
+
+        if (47..63).contains(&t) && msb_differs != matches!(t, 49 | 59) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn find_block0(ihv: [u32; 4], rng: &mut Xorshift) -> [u32; 16] {
+    let mut q = initial_q(ihv);
+    let mut block = [0u32; 16];
+
+    loop {
+        q[Q_OFFSET + 1] = rng.next();
+        q[Q_OFFSET + 3] = (rng.next() & 0xfe87bc3f) | 0x017841c0;
+        q[Q_OFFSET + 4] = (rng.next() & 0x44000033) | 0x000002c0 | (q[Q_OFFSET + 3] & 0x0287bc00);
+        q[Q_OFFSET + 5] = 0x41ffffc8 | (q[Q_OFFSET + 4] & 0x04000033);
+        q[Q_OFFSET + 6] = 0xb84b82d6;
+        q[Q_OFFSET + 7] = (rng.next() & 0x68000084) | 0x02401b43;
+        q[Q_OFFSET + 8] = (rng.next() & 0x2b8f6e04) | 0x005090d3 | (!q[Q_OFFSET + 7] & 0x40000000);
+        q[Q_OFFSET + 9] =
+            0x20040068 | (q[Q_OFFSET + 8] & 0x00020000) | (!q[Q_OFFSET + 8] & 0x40000000);
+        q[Q_OFFSET + 10] = (rng.next() & 0x40000000) | 0x1040b089;
+        q[Q_OFFSET + 11] =
+            (rng.next() & 0x10408008) | 0x0fbb7f16 | (!q[Q_OFFSET + 10] & 0x40000000);
+        q[Q_OFFSET + 12] =
+            (rng.next() & 0x1ed9df7f) | 0x00022080 | (!q[Q_OFFSET + 11] & 0x40200000);
+        q[Q_OFFSET + 13] = (rng.next() & 0x5efb4f77) | 0x20049008;
+        q[Q_OFFSET + 14] =
+            (rng.next() & 0x1fff5f77) | 0x0000a088 | (!q[Q_OFFSET + 13] & 0x40000000);
+        q[Q_OFFSET + 15] =
+            (rng.next() & 0x5efe7ff7) | 0x80008000 | (!q[Q_OFFSET + 14] & 0x00010000);
+        q[Q_OFFSET + 16] =
+            (rng.next() & 0x1ffdffff) | 0xa0000000 | (!q[Q_OFFSET + 15] & 0x40020000);
+
+        for t in [0, 6, 7, 11, 14, 15] {
+            block[t] = reverse_step(&q, t);
+        }
+
+        // Q17 is chosen directly, m1 then follows and with it Q2, which has
+        // no conditions in the first block.
+        let mut found = false;
+
+        for _ in 0..(1 << 7) {
+            q[Q_OFFSET + 17] =
+                ((rng.next() & 0x3ffd7ff7) | (q[Q_OFFSET + 16] & 0xc0008008)) ^ 0x40000000;
+
+            forward_step(&mut q, 17, block[6]);
+            if (q[Q_OFFSET + 18] ^ q[Q_OFFSET + 17]) & 0xa0020000 != 0x00020000 {
+                continue;
+            }
+
+            forward_step(&mut q, 18, block[11]);
+            if q[Q_OFFSET + 19] & 0x80020000 != 0x80000000 {
+                continue;
+            }
+
+            forward_step(&mut q, 19, block[0]);
+            if (q[Q_OFFSET + 20] ^ q[Q_OFFSET + 19]) & 0x80040000 != 0x00040000 {
+                continue;
+            }
+
+            block[1] = q[Q_OFFSET + 17]
+                .wrapping_sub(q[Q_OFFSET + 16])
+                .rotate_right(5)
+                .wrapping_sub(step_sum(&q, 16, 0));
+            forward_step(&mut q, 1, block[1]);
+            block[5] = reverse_step(&q, 5);
+            block[2] = reverse_step(&q, 2);
+
+            found = true;
+            break;
+        }
+
+        if !found {
+            continue;
+        }
+
+        let q4 = q[Q_OFFSET + 4];
+        let q9 = q[Q_OFFSET + 9];
+        let q10 = q[Q_OFFSET + 10];
+
+        for q4_tunnel in subsets(0x38000004) {
+            q[Q_OFFSET + 4] = q4 ^ q4_tunnel;
+            block[5] = reverse_step(&q, 5);
+
+            forward_step(&mut q, 20, block[5]);
+            if (q[Q_OFFSET + 21] ^ q[Q_OFFSET + 20]) & 0x80020000 != 0 {
+                continue;
+            }
+
+            for t in [3, 4, 7] {
+                block[t] = reverse_step(&q, t);
+            }
+
+            for q9_q10_tunnel in subsets(0x2060) {
+                q[Q_OFFSET + 9] = q9 ^ (q9_q10_tunnel & 0x2000);
+                q[Q_OFFSET + 10] = q10 ^ (q9_q10_tunnel & 0x60);
+                block[10] = reverse_step(&q, 10);
+
+                forward_step(&mut q, 21, block[10]);
+                if q[Q_OFFSET + 22] & 0x80000000 == 0 {
+                    continue;
+                }
+
+                if step_sum(&q, 22, block[15]) & 0x20000 != 0 {
+                    continue;
+                }
+
+                forward_step(&mut q, 22, block[15]);
+                if q[Q_OFFSET + 23] & 0x80000000 != 0 {
+                    continue;
+                }
+
+                forward_step(&mut q, 23, block[4]);
+                if q[Q_OFFSET + 24] & 0x80000000 == 0 {
+                    continue;
+                }
+
+                block[13] = reverse_step(&q, 13);
+                let q9 = q[Q_OFFSET + 9];
+
+                for q9_tunnel in subsets(0x0eb94f16) {
+                    q[Q_OFFSET + 9] = q9 ^ q9_tunnel;
+
+                    for t in [8, 9, 12] {
+                        block[t] = reverse_step(&q, t);
+                    }
+
+                    if !block0_tail(&mut q, &block) {
+                        continue;
+                    }
+
+                    let chaining = [
+                        q[Q_OFFSET + 61].wrapping_add(ihv[0]),
+                        q[Q_OFFSET + 64].wrapping_add(ihv[1]),
+                        q[Q_OFFSET + 63].wrapping_add(ihv[2]),
+                        q[Q_OFFSET + 62].wrapping_add(ihv[3]),
+                    ];
+
+                    if suits_block1(chaining) && is_near_collision(ihv, &block) {
+                        return block;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs `steps` for both messages of the second block and tells whether the
+/// second message stays on `BLOCK1_PATH`, given that it did before.
+fn follows_block1_path(
+    q: &mut [u32; 68],
+    other: &mut [u32; 68],
+    block: &[u32; 16],
+    steps: Range<usize>,
+) -> bool {
+    for index in Q_OFFSET + steps.start - 3..=Q_OFFSET + steps.start {
+        if index > Q_OFFSET {
+            other[index] = q[index] ^ BLOCK1_PATH[index - Q_OFFSET - 1];
+        }
+    }
+
+    for t in steps {
+        let word = word_index(t);
+        forward_step(q, t, block[word]);
+        forward_step(other, t, block[word].wrapping_add(BLOCK1_DIFFERENCE[word]));
+
+        if q[Q_OFFSET + t + 1] ^ other[Q_OFFSET + t + 1] != BLOCK1_PATH[t] {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Picks `Q[t + 1]` for the round one `steps` according to
+/// `BLOCK1_CONDITIONS`, retrying a step whose carries leave the path.
+fn sample_block1(
+    q: &mut [u32; 68],
+    other: &mut [u32; 68],
+    block: &mut [u32; 16],
+    steps: Range<usize>,
+    rng: &mut Xorshift,
+) -> bool {
+    for t in steps {
+        let [mut zeros, mut ones, same] = BLOCK1_CONDITIONS[t];
+
+        // Flipping the most significant bit of every Q keeps the path, the
+        // table is written for a chaining value whose b has it cleared.
+        let msb = q[Q_OFFSET] & 0x80000000;
+        zeros ^= msb;
+        ones ^= msb;
+
+        let free = !(zeros | ones | same);
+
+        let mut attempts = 0;
+
+        loop {
+            q[Q_OFFSET + t + 1] = (rng.next() & free) | ones | (q[Q_OFFSET + t] & same);
+            block[t] = reverse_step(q, t);
+
+            if follows_block1_path(q, other, block, t..t + 1) {
+                break;
+            }
+
+            attempts += 1;
+            if attempts == 64 {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn find_block1(ihv: [u32; 4], rng: &mut Xorshift) -> [u32; 16] {
+    let other_ihv: [u32; 4] =
+        std::array::from_fn(|index| ihv[index].wrapping_add(NEAR_COLLISION[index]));
+    let mut q = initial_q(ihv);
+    let mut other = initial_q(other_ihv);
+    let mut block = [0u32; 16];
+
+    loop {
+        if !sample_block1(&mut q, &mut other, &mut block, 0..14, rng) {
+            continue;
+        }
+
+        // Q15 and Q16 only feed m14 and m15, which round two uses late.
+        for _ in 0..(1 << 10) {
+            if !sample_block1(&mut q, &mut other, &mut block, 14..16, rng) {
+                continue;
+            }
+
+            if !follows_block1_path(&mut q, &mut other, &block, 16..23) {
+                continue;
+            }
+
+            let q4 = q[Q_OFFSET + 4];
+
+            for q4_tunnel in subsets(BLOCK1_Q4_TUNNEL) {
+                q[Q_OFFSET + 4] = q4 ^ q4_tunnel;
+
+                for t in [3, 4, 7] {
+                    block[t] = reverse_step(&q, t);
+                }
+
+                if !follows_block1_path(&mut q, &mut other, &block, 3..8)
+                    || !follows_block1_path(&mut q, &mut other, &block, 23..24)
+                {
+                    continue;
+                }
+
+                let q9 = q[Q_OFFSET + 9];
+
+                for q9_tunnel in subsets(BLOCK1_Q9_TUNNEL) {
+                    q[Q_OFFSET + 9] = q9 ^ q9_tunnel;
+
+                    for t in [8, 9, 12] {
+                        block[t] = reverse_step(&q, t);
+                    }
+
+                    if !follows_block1_path(&mut q, &mut other, &block, 8..13)
+                        || !follows_block1_path(&mut q, &mut other, &block, 24..60)
+                    {
+                        continue;
+                    }
+
+                    let mut first = ihv;
+                    let mut second = other_ihv;
+                    compress_words(&mut first, &block);
+                    compress_words(&mut second, &with_difference(&block, &BLOCK1_DIFFERENCE));
+
+                    if first == second {
+                        return block;
+                    }
+                }
+
+                q[Q_OFFSET + 9] = q9;
+
+                for t in [8, 9, 12] {
+                    block[t] = reverse_step(&q, t);
+                }
+            }
+
+            q[Q_OFFSET + 4] = q4;
+
+            for t in [3, 4, 7] {
+                block[t] = reverse_step(&q, t);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_bytes;
+
+    #[test]
+    fn blocks_collide_after_prefix() {
+        let collision = find_with_seed(b"identical prefix", 416);
+        let (first, second) = collision.messages();
+
+        assert_eq!(64, collision.prefix.len());
+        assert!(collision.prefix.starts_with(b"identical prefix"));
+        assert_ne!(first, second);
+        assert_eq!(digest_bytes(&first), digest_bytes(&second));
+
+        let suffix = b"any common suffix";
+        assert_eq!(
+            digest_bytes(&[&first[..], suffix].concat()),
+            digest_bytes(&[&second[..], suffix].concat())
+        );
+    }
+
+    #[test]
+    fn subsets_enumerate_every_combination() {
+        let mut values: Vec<u32> = subsets(0b1011).collect();
+        values.sort();
+
+        assert_eq!(vec![0, 1, 2, 3, 8, 9, 10, 11], values);
+    }
+}
@@ -0,0 +1,247 @@
+//! MD5-crypt password hashing, as introduced by FreeBSD and found in glibc
+//! `crypt(3)`, and its Apache `htpasswd` variant apr1.
+//!
+//! A hash reads `$1$salt$checksum`, or `$apr1$salt$checksum`, where the salt
+//! is at most 8 characters and the checksum is 22 characters of the crypt
+//! base64 alphabet `./0-9A-Za-z`. The two schemes only differ by their magic
+//! prefix, which is hashed along with the password and the salt. The digest is
+//! then stretched by 1000 rounds mixing the password, the salt and the previous
+//! digest.
+//!
+//! MD5-crypt is kept for legacy systems only, it is far too fast to resist
+//! offline guessing.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::{Digest, Md5};
+
+const ALPHABET: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ROUNDS: usize = 1000;
+const MAX_SALT_LENGTH: usize = 8;
+
+/// Digest bytes grouped by three, in the order they are encoded.
+const GROUPS: [[usize; 3]; 5] = [[0, 6, 12], [1, 7, 13], [2, 8, 14], [3, 9, 15], [4, 10, 5]];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// `$1$`, FreeBSD and glibc.
+    Md5Crypt,
+    /// `$apr1$`, Apache `htpasswd -m`.
+    Apr1,
+}
+
+impl Scheme {
+    pub fn magic(self) -> &'static str {
+        match self {
+            Scheme::Md5Crypt => "$1$",
+            Scheme::Apr1 => "$apr1$",
+        }
+    }
+}
+
+/// The salt as used by the algorithm: without the magic prefix, up to the
+/// next `$` and at most 8 bytes long.
+fn effective_salt(salt: &str, scheme: Scheme) -> &str {
+    let salt = salt.strip_prefix(scheme.magic()).unwrap_or(salt);
+    let salt = salt.split('$').next().unwrap_or_default();
+
+    let mut end = salt.len().min(MAX_SALT_LENGTH);
+    while !salt.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &salt[..end]
+}
+
+fn stretch(password: &[u8], salt: &[u8], magic: &[u8]) -> Digest {
+    let mut alternate = Md5::new();
+    alternate.update(password);
+    alternate.update(salt);
+    alternate.update(password);
+    let alternate = alternate.finalize();
+
+    let mut hasher = Md5::new();
+    hasher.update(password);
+    hasher.update(magic);
+    hasher.update(salt);
+
+    for chunk in password.chunks(16) {
+        hasher.update(&alternate.0[..chunk.len()]);
+    }
+
+    // Historical quirk: a zero byte for every set bit of the length and the
+    // first password byte for every clear one.
+    let mut length = password.len();
+    while length > 0 {
+        match length & 1 {
+            1 => hasher.update(&[0]),
+            _ => hasher.update(&password[..1]),
+        }
+        length >>= 1;
+    }
+
+    let mut digest = hasher.finalize();
+
+    for round in 0..ROUNDS {
+        let mut hasher = Md5::new();
+
+        match round % 2 {
+            1 => hasher.update(password),
+            _ => hasher.update(&digest.0),
+        }
+
+        if round % 3 != 0 {
+            hasher.update(salt);
+        }
+
+        if round % 7 != 0 {
+            hasher.update(password);
+        }
+
+        match round % 2 {
+            1 => hasher.update(&digest.0),
+            _ => hasher.update(password),
+        }
+
+        digest = hasher.finalize();
+    }
+
+    digest
+}
+
+fn encode(digest: &Digest, output: &mut String) {
+    let mut push = |mut value: u32, digits: usize| {
+        for _ in 0..digits {
+            output.push(ALPHABET[(value & 0x3f) as usize] as char);
+            value >>= 6;
+        }
+    };
+
+    for [first, second, third] in GROUPS {
+        let bytes = [digest.0[first], digest.0[second], digest.0[third]];
+        push(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]), 4);
+    }
+
+    push(digest.0[11] as u32, 2);
+}
+
+/// Hashes `password` with an explicit salt, which may carry the magic prefix
+/// and a trailing checksum, so an existing hash can be passed as the salt.
+pub fn hash_with_salt(password: &[u8], salt: &str, scheme: Scheme) -> String {
+    let salt = effective_salt(salt, scheme);
+    let digest = stretch(password, salt.as_bytes(), scheme.magic().as_bytes());
+
+    let mut hash = String::with_capacity(scheme.magic().len() + salt.len() + 23);
+    hash.push_str(scheme.magic());
+    hash.push_str(salt);
+    hash.push('$');
+    encode(&digest, &mut hash);
+
+    hash
+}
+
+/// A random 8 character salt. Salts only need to be unique, the keys the
+/// standard library draws from the OS for `RandomState` are enough for that.
+pub fn generate_salt() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    let mut bits = hasher.finish();
+
+    (0..MAX_SALT_LENGTH)
+        .map(|_| {
+            let char = ALPHABET[(bits & 0x3f) as usize] as char;
+            bits >>= 6;
+            char
+        })
+        .collect()
+}
+
+/// Hashes `password` with a fresh random salt.
+pub fn hash(password: &[u8], scheme: Scheme) -> String {
+    hash_with_salt(password, &generate_salt(), scheme)
+}
+
+/// Checks `password` against a `$1$` or `$apr1$` hash, comparing in constant
+/// time. Hashes of other schemes never match.
+pub fn verify(password: &[u8], hash: &str) -> bool {
+    let scheme = if hash.starts_with(Scheme::Md5Crypt.magic()) {
+        Scheme::Md5Crypt
+    } else if hash.starts_with(Scheme::Apr1.magic()) {
+        Scheme::Apr1
+    } else {
+        return false;
+    };
+
+    let expected = hash_with_salt(password, hash, scheme);
+    hasher::ct_eq(expected.as_bytes(), hash.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glibc_and_htpasswd_test_vectors() {
+        let test_cases: &[(&[u8], &str, Scheme, &str)] = &[
+            // glibc crypt/md5c-test.c
+            (
+                b"Hello world!",
+                "$1$saltstring",
+                Scheme::Md5Crypt,
+                "$1$saltstri$YMyguxXMBpd2TEZ.vS/3q1",
+            ),
+            // Apache htpasswd documentation
+            (
+                b"myPassword",
+                "r31.....",
+                Scheme::Apr1,
+                "$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/",
+            ),
+            // Cross-checked with `openssl passwd`
+            (b"", "", Scheme::Md5Crypt, "$1$$qRPK7m23GJusamGpoGLby/"),
+            (
+                b"",
+                "abcdefgh",
+                Scheme::Apr1,
+                "$apr1$abcdefgh$L.PT565ESX4Tp2bqNs7Ie.",
+            ),
+            (
+                &[b'a'; 40],
+                "xy",
+                Scheme::Md5Crypt,
+                "$1$xy$pUub5uX9loeQqtCXjIwBI0",
+            ),
+        ];
+
+        for &(password, salt, scheme, expected) in test_cases {
+            assert_eq!(expected, hash_with_salt(password, salt, scheme));
+            assert!(verify(password, expected), "Failed hash: {}", expected);
+        }
+    }
+
+    #[test]
+    fn verify_rejects_wrong_passwords_and_schemes() {
+        let hash = hash(b"secret", Scheme::Md5Crypt);
+
+        assert!(verify(b"secret", &hash));
+        assert!(!verify(b"Secret", &hash));
+        assert!(!verify(b"secret", &hash[..hash.len() - 1]));
+        assert!(!verify(b"secret", &hash.replacen("$1$", "$apr1$", 1)));
+        assert!(!verify(b"secret", "$5$rounds=5000$salt$hash"));
+        assert!(!verify(b"secret", ""));
+    }
+
+    #[test]
+    fn salts_are_truncated_and_random() {
+        assert_eq!(
+            hash_with_salt(b"password", "$1$abcdefgh", Scheme::Md5Crypt),
+            hash_with_salt(b"password", "abcdefghijkl$ignored", Scheme::Md5Crypt)
+        );
+
+        let salt = generate_salt();
+        assert_eq!(8, salt.len());
+        assert!(salt.bytes().all(|byte| ALPHABET.contains(&byte)));
+        assert_ne!(salt, generate_salt());
+    }
+}
@@ -0,0 +1,62 @@
+//! Length-extension attack on `md5(secret || message)`.
+//!
+//! An MD5 digest is the full chaining state after the padded message, so
+//! anyone who knows `md5(secret || message)` and the length of
+//! `secret || message` can keep hashing from that state and obtain
+//! `md5(secret || message || glue_padding || suffix)` without the secret.
+
+use crate::{padding, Digest, Md5};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    /// Padding `md5::digest` appended to the original message, it has to be
+    /// sent between the original message and the suffix.
+    pub glue_padding: Vec<u8>,
+    /// Digest of `secret || message || glue_padding || suffix`.
+    pub digest: Digest,
+}
+
+/// Forges the digest of the original message extended with `suffix`, given
+/// only its digest and the length of the secret-prefixed message.
+pub fn extend(digest: Digest, message_len: u64, suffix: &[u8]) -> Extension {
+    let (glue_padding, glue_padding_len) = padding(message_len);
+
+    let mut hasher = Md5::from_state(digest.into(), message_len + glue_padding_len as u64);
+    hasher.update(suffix);
+
+    Extension {
+        glue_padding: glue_padding[..glue_padding_len].to_vec(),
+        digest: hasher.finalize(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_bytes;
+
+    #[test]
+    fn forged_digest_matches_real_one() {
+        let secret = b"attack at dawn, secretly";
+        let suffix = b"&role=admin";
+
+        for message_len in 0..150 {
+            let message: Vec<u8> = (0..message_len).map(|i| b'a' + (i % 26) as u8).collect();
+            let original = [&secret[..], &message].concat();
+
+            let extension = extend(digest_bytes(&original), original.len() as u64, suffix);
+            let forged_message = [&original[..], &extension.glue_padding, suffix].concat();
+
+            assert_eq!(0, (original.len() + extension.glue_padding.len()) % 64);
+            assert_eq!(digest_bytes(&forged_message), extension.digest);
+        }
+    }
+
+    #[test]
+    fn digest_round_trips_through_state() {
+        let digest = digest_bytes(b"message digest");
+        let state: [u32; 4] = digest.into();
+
+        assert_eq!(digest, Digest::from(state));
+    }
+}
@@ -1,150 +1,447 @@
+//! MD5 (RFC 1321) and HMAC-MD5.
+//!
+//! Without the default `std` feature the crate is `no_std` and never
+//! allocates: only the hasher, the digest type and its hex and base64
+//! parsing are available, the other modules need an operating system.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// The tests allocate, the code under test stays `no_std`.
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+use core::fmt;
+use core::str::FromStr;
+
+use hasher::encoding::{self, Base64};
+use hasher::BlockBuffer;
+
+pub use hasher::encoding::DecodeError;
+
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Digest(pub [u8; 16]);
 
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, formatter)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u32; 4]> for Digest {
+    fn from(state: [u32; 4]) -> Self {
+        let mut result = [0u8; 16];
+
+        for (bytes, word) in result.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        Digest(result)
+    }
+}
+
+impl From<Digest> for [u32; 4] {
+    fn from(digest: Digest) -> Self {
+        let mut state = [0u32; 4];
+
+        for (word, bytes) in state.iter_mut().zip(digest.0.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        state
+    }
+}
+
+impl TryFrom<&[u8]> for Digest {
+    type Error = DecodeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Digest)
+            .map_err(|_| DecodeError::InvalidLength)
+    }
+}
+
+/// Parses the hex form, in either case.
+impl FromStr for Digest {
+    type Err = DecodeError;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        let mut digest = [0u8; 16];
+        encoding::decode_hex(hex, &mut digest)?;
+        Ok(Digest(digest))
+    }
+}
+
 impl Digest {
-    pub fn to_string(&self) -> String {
-        self.0.iter().map(|&byte| format!("{:02x}", byte)).collect()
+    #[cfg(feature = "std")]
+    fn encode(&self, variant: Base64) -> String {
+        let mut text = String::with_capacity(24);
+        encoding::encode_base64(&self.0, variant, &mut text)
+            .expect("Writing to a String cannot fail");
+        text
+    }
+
+    fn decode(text: &str, variant: Base64) -> Result<Self, DecodeError> {
+        let mut digest = [0u8; 16];
+        encoding::decode_base64(text, variant, &mut digest)?;
+        Ok(Digest(digest))
+    }
+
+    /// Standard base64 with padding, as in `Content-MD5` headers.
+    #[cfg(feature = "std")]
+    pub fn to_base64(&self) -> String {
+        self.encode(Base64::Standard)
+    }
+
+    /// URL and file name safe base64 without padding.
+    #[cfg(feature = "std")]
+    pub fn to_base64url(&self) -> String {
+        self.encode(Base64::UrlSafe)
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self, DecodeError> {
+        Digest::decode(text, Base64::Standard)
+    }
+
+    pub fn from_base64url(text: &str) -> Result<Self, DecodeError> {
+        Digest::decode(text, Base64::UrlSafe)
+    }
+
+    /// Compares in constant time, to be used on secret values such as MACs.
+    pub fn ct_eq(&self, other: &Digest) -> bool {
+        hasher::ct_eq(&self.0, &other.0)
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Digest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        encoding::serde::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Digest {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        encoding::serde::deserialize(deserializer)
+    }
+}
+
+const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// `K[i] = floor(2^32 * |sin(i + 1)|)`, precomputed so no floating point is
+/// involved at runtime.
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// Expands to the 64 MD5 steps, each one delegated to `$step` so the same
+/// schedule drives both the scalar and the vectorized compression functions.
+macro_rules! md5_steps {
+    ($step:ident, $a:ident, $b:ident, $c:ident, $d:ident, $x:ident) => {
+        $step!(f, $a, $b, $c, $d, $x[0], K[0], 7);
+        $step!(f, $d, $a, $b, $c, $x[1], K[1], 12);
+        $step!(f, $c, $d, $a, $b, $x[2], K[2], 17);
+        $step!(f, $b, $c, $d, $a, $x[3], K[3], 22);
+        $step!(f, $a, $b, $c, $d, $x[4], K[4], 7);
+        $step!(f, $d, $a, $b, $c, $x[5], K[5], 12);
+        $step!(f, $c, $d, $a, $b, $x[6], K[6], 17);
+        $step!(f, $b, $c, $d, $a, $x[7], K[7], 22);
+        $step!(f, $a, $b, $c, $d, $x[8], K[8], 7);
+        $step!(f, $d, $a, $b, $c, $x[9], K[9], 12);
+        $step!(f, $c, $d, $a, $b, $x[10], K[10], 17);
+        $step!(f, $b, $c, $d, $a, $x[11], K[11], 22);
+        $step!(f, $a, $b, $c, $d, $x[12], K[12], 7);
+        $step!(f, $d, $a, $b, $c, $x[13], K[13], 12);
+        $step!(f, $c, $d, $a, $b, $x[14], K[14], 17);
+        $step!(f, $b, $c, $d, $a, $x[15], K[15], 22);
+
+        $step!(g, $a, $b, $c, $d, $x[1], K[16], 5);
+        $step!(g, $d, $a, $b, $c, $x[6], K[17], 9);
+        $step!(g, $c, $d, $a, $b, $x[11], K[18], 14);
+        $step!(g, $b, $c, $d, $a, $x[0], K[19], 20);
+        $step!(g, $a, $b, $c, $d, $x[5], K[20], 5);
+        $step!(g, $d, $a, $b, $c, $x[10], K[21], 9);
+        $step!(g, $c, $d, $a, $b, $x[15], K[22], 14);
+        $step!(g, $b, $c, $d, $a, $x[4], K[23], 20);
+        $step!(g, $a, $b, $c, $d, $x[9], K[24], 5);
+        $step!(g, $d, $a, $b, $c, $x[14], K[25], 9);
+        $step!(g, $c, $d, $a, $b, $x[3], K[26], 14);
+        $step!(g, $b, $c, $d, $a, $x[8], K[27], 20);
+        $step!(g, $a, $b, $c, $d, $x[13], K[28], 5);
+        $step!(g, $d, $a, $b, $c, $x[2], K[29], 9);
+        $step!(g, $c, $d, $a, $b, $x[7], K[30], 14);
+        $step!(g, $b, $c, $d, $a, $x[12], K[31], 20);
+
+        $step!(h, $a, $b, $c, $d, $x[5], K[32], 4);
+        $step!(h, $d, $a, $b, $c, $x[8], K[33], 11);
+        $step!(h, $c, $d, $a, $b, $x[11], K[34], 16);
+        $step!(h, $b, $c, $d, $a, $x[14], K[35], 23);
+        $step!(h, $a, $b, $c, $d, $x[1], K[36], 4);
+        $step!(h, $d, $a, $b, $c, $x[4], K[37], 11);
+        $step!(h, $c, $d, $a, $b, $x[7], K[38], 16);
+        $step!(h, $b, $c, $d, $a, $x[10], K[39], 23);
+        $step!(h, $a, $b, $c, $d, $x[13], K[40], 4);
+        $step!(h, $d, $a, $b, $c, $x[0], K[41], 11);
+        $step!(h, $c, $d, $a, $b, $x[3], K[42], 16);
+        $step!(h, $b, $c, $d, $a, $x[6], K[43], 23);
+        $step!(h, $a, $b, $c, $d, $x[9], K[44], 4);
+        $step!(h, $d, $a, $b, $c, $x[12], K[45], 11);
+        $step!(h, $c, $d, $a, $b, $x[15], K[46], 16);
+        $step!(h, $b, $c, $d, $a, $x[2], K[47], 23);
+
+        $step!(i, $a, $b, $c, $d, $x[0], K[48], 6);
+        $step!(i, $d, $a, $b, $c, $x[7], K[49], 10);
+        $step!(i, $c, $d, $a, $b, $x[14], K[50], 15);
+        $step!(i, $b, $c, $d, $a, $x[5], K[51], 21);
+        $step!(i, $a, $b, $c, $d, $x[12], K[52], 6);
+        $step!(i, $d, $a, $b, $c, $x[3], K[53], 10);
+        $step!(i, $c, $d, $a, $b, $x[10], K[54], 15);
+        $step!(i, $b, $c, $d, $a, $x[1], K[55], 21);
+        $step!(i, $a, $b, $c, $d, $x[8], K[56], 6);
+        $step!(i, $d, $a, $b, $c, $x[15], K[57], 10);
+        $step!(i, $c, $d, $a, $b, $x[6], K[58], 15);
+        $step!(i, $b, $c, $d, $a, $x[13], K[59], 21);
+        $step!(i, $a, $b, $c, $d, $x[4], K[60], 6);
+        $step!(i, $d, $a, $b, $c, $x[11], K[61], 10);
+        $step!(i, $c, $d, $a, $b, $x[2], K[62], 15);
+        $step!(i, $b, $c, $d, $a, $x[9], K[63], 21);
+    };
+}
+
+#[inline(always)]
 fn f(x: u32, y: u32, z: u32) -> u32 {
     x & y | !x & z
 }
 
+#[inline(always)]
 fn g(x: u32, y: u32, z: u32) -> u32 {
     x & z | y & !z
 }
 
+#[inline(always)]
 fn h(x: u32, y: u32, z: u32) -> u32 {
     x ^ y ^ z
 }
 
+#[inline(always)]
 fn i(x: u32, y: u32, z: u32) -> u32 {
     y ^ (x | !z)
 }
 
-pub fn digest(input: &str) -> Digest {
-    let mut data: Vec<u8> = input.as_bytes().to_vec();
-    let data_len_bits = (data.len() as u64) * 8;
-    data.push(0x80);
+pub(crate) fn compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut x = [0u32; 16];
 
-    while data.len() % 64 != 56 {
-        data.push(0);
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
     }
 
-    data.extend_from_slice(&data_len_bits.to_le_bytes());
+    compress_words(state, &x);
+}
+
+pub(crate) fn compress_words(state: &mut [u32; 4], x: &[u32; 16]) {
+    let [mut a, mut b, mut c, mut d] = *state;
 
-    let mut a: u32 = 0x67452301;
-    let mut b: u32 = 0xefcdab89;
-    let mut c: u32 = 0x98badcfe;
-    let mut d: u32 = 0x10325476;
+    macro_rules! step {
+        ($func:ident, $a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $k:expr, $s:expr) => {
+            $a = $b.wrapping_add(
+                $a.wrapping_add($func($b, $c, $d))
+                    .wrapping_add($x)
+                    .wrapping_add($k)
+                    .rotate_left($s),
+            );
+        };
+    }
 
-    let table_values: [u32; 64] = {
-        let mut buff = [0u32; 64];
+    md5_steps!(step, a, b, c, d, x);
 
-        for i in 0..64 {
-            buff[i] = (2_u64.pow(32) as f64 * ((i + 1) as f64).sin().abs()) as u32;
-        }
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
 
-        buff
-    };
+/// Incremental MD5 hasher.
+#[derive(Clone, Debug)]
+pub struct Md5 {
+    state: [u32; 4],
+    buffer: BlockBuffer<64>,
+}
 
-    for chunk in data.chunks(64) {
-        let mut x = [0u32; 16];
+impl Default for Md5 {
+    fn default() -> Self {
+        Md5::new()
+    }
+}
 
-        for (i, word) in chunk.chunks(4).enumerate() {
-            x[i] = u32::from_le_bytes(word.try_into().unwrap());
+impl Md5 {
+    pub fn new() -> Self {
+        Md5 {
+            state: INITIAL_STATE,
+            buffer: BlockBuffer::new(),
         }
+    }
 
-        let (aa, bb, cc, dd) = (a, b, c, d);
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| compress(state, block));
+    }
 
-        macro_rules! round {
-            ($func:ident, $a:ident, $b:ident, $c:ident, $d:ident, $k:expr, $s:expr, $i:expr) => {
-                $a = $b.wrapping_add(
-                    ($a.wrapping_add($func($b, $c, $d))
-                        .wrapping_add(x[$k])
-                        .wrapping_add(table_values[$i]))
-                    .rotate_left($s),
-                );
-            };
-        }
+    /// Resumes hashing from the chaining value `state` reached after
+    /// `length` bytes, which have to make up whole blocks.
+    pub fn from_state(state: [u32; 4], length: u64) -> Self {
+        assert!(
+            length.is_multiple_of(64),
+            "Processed length must be a multiple of the block size"
+        );
 
-        struct Round {
-            func: fn(u32, u32, u32) -> u32,
-            k: [u32; 16],
-            s: [u32; 16],
+        Md5 {
+            state,
+            buffer: BlockBuffer::from_parts(length as u128, &[]),
         }
+    }
 
-        let rounds: [Round; 4] = [
-            Round {
-                func: f,
-                k: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
-                s: [7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22],
-            },
-            Round {
-                func: g,
-                k: [1, 6, 11, 0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12],
-                s: [5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20],
-            },
-            Round {
-                func: h,
-                k: [5, 8, 11, 14, 1, 4, 7, 10, 13, 0, 3, 6, 9, 12, 15, 2],
-                s: [4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23],
-            },
-            Round {
-                func: i,
-                k: [0, 7, 14, 5, 12, 3, 10, 1, 8, 15, 6, 13, 4, 11, 2, 9],
-                s: [6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21],
-            },
-        ];
+    pub fn finalize(self) -> Digest {
+        self.finalize_bits(0, 0)
+    }
 
-        for (round_index, round) in rounds.iter().enumerate() {
-            for i in 0..16 {
-                let func = round.func;
-                let k_value = round.k[i] as usize;
-                let table_value_index = round_index * 16 + i;
-                round!(func, a, b, c, d, k_value, round.s[i], table_value_index);
-                (a, b, c, d) = (d, a, b, c);
-            }
-        }
+    /// Ends the message with the `bit_count` most significant bits of
+    /// `last_byte`, for messages whose bit length is not a multiple of 8.
+    /// Bits are taken most significant first as in RFC 1321, the low bits of
+    /// `last_byte` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// When `bit_count` is 8 or more.
+    pub fn finalize_bits(mut self, last_byte: u8, bit_count: u32) -> Digest {
+        assert!(bit_count < 8, "A partial byte has at most 7 bits");
+
+        let data_len_bits = (self.buffer.length() as u64)
+            .wrapping_mul(8)
+            .wrapping_add(bit_count as u64);
+
+        let state = &mut self.state;
+        self.buffer.pad_bits(
+            last_byte,
+            bit_count,
+            &data_len_bits.to_le_bytes(),
+            |block| compress(state, block),
+        );
 
-        a = a.wrapping_add(aa);
-        b = b.wrapping_add(bb);
-        c = c.wrapping_add(cc);
-        d = d.wrapping_add(dd);
+        Digest::from(self.state)
     }
+}
+
+/// Merkle–Damgård strengthening of a `length` bytes long message: the `0x80`
+/// marker, zeros up to 56 bytes modulo 64 and the bit length in little-endian.
+/// Returns the padding buffer and how many of its bytes are used.
+pub fn padding(length: u64) -> ([u8; 72], usize) {
+    let zeros_len = (55 - (length % 64) as i64).rem_euclid(64) as usize;
+    let data_len_bits = length.wrapping_mul(8);
 
-    let mut result = [0u8; 16];
-    result[..4].copy_from_slice(&a.to_le_bytes());
-    result[4..8].copy_from_slice(&b.to_le_bytes());
-    result[8..12].copy_from_slice(&c.to_le_bytes());
-    result[12..].copy_from_slice(&d.to_le_bytes());
+    let mut padding = [0u8; 72];
+    padding[0] = 0x80;
+    padding[1 + zeros_len..9 + zeros_len].copy_from_slice(&data_len_bits.to_le_bytes());
 
-    Digest(result)
+    (padding, 9 + zeros_len)
 }
 
-pub mod ffi {
-    use std::ffi::{CStr, CString};
-    use std::os::raw::c_char;
+impl hasher::Hasher for Md5 {
+    const BLOCK_SIZE: usize = 64;
 
-    #[no_mangle]
-    pub extern "C" fn md5(raw_input: *const c_char) -> *mut c_char {
-        let input = unsafe {
-            assert!(!raw_input.is_null());
-            CStr::from_ptr(raw_input)
-        }
-        .to_str()
-        .unwrap_or_default();
+    type Output = Digest;
+
+    fn new() -> Self {
+        Md5::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Md5::update(self, data)
+    }
 
-        CString::new(crate::digest(input).to_string())
-            .unwrap_or_default()
-            .into_raw()
+    fn finalize(self) -> Digest {
+        Md5::finalize(self)
     }
 }
 
+pub type HmacMd5 = hasher::Hmac<Md5>;
+
+pub fn hmac(key: &[u8], data: &[u8]) -> Digest {
+    HmacMd5::mac(key, data)
+}
+
+pub fn digest_bytes(input: &[u8]) -> Digest {
+    let mut hasher = Md5::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Digest of the first `bit_len` bits of `input`, see [`Md5::finalize_bits`].
+///
+/// # Panics
+///
+/// When `bit_len` exceeds the bits of `input`.
+pub fn digest_bits(input: &[u8], bit_len: u64) -> Digest {
+    assert!(
+        bit_len <= input.len() as u64 * 8,
+        "Bit length exceeds the data"
+    );
+
+    let whole_bytes = (bit_len / 8) as usize;
+    let mut hasher = Md5::new();
+    hasher.update(&input[..whole_bytes]);
+
+    match bit_len % 8 {
+        0 => hasher.finalize(),
+        bit_count => hasher.finalize_bits(input[whole_bytes], bit_count as u32),
+    }
+}
+
+pub fn digest(input: &str) -> Digest {
+    digest_bytes(input.as_bytes())
+}
+
+#[cfg(feature = "std")]
+pub mod collision;
+#[cfg(feature = "std")]
+pub mod crypt;
+#[cfg(feature = "std")]
+pub mod length_extension;
+#[cfg(feature = "std")]
+pub mod midstate;
+#[cfg(feature = "std")]
+pub mod multi;
+#[cfg(feature = "std")]
+pub mod rainbow;
+
 #[cfg(test)]
 mod tests {
-    use super::digest;
+    use super::{digest, digest_bits, digest_bytes, hmac, DecodeError, Digest, HmacMd5, Md5, K};
+    #[cfg(not(feature = "std"))]
+    use std::{string::ToString, vec, vec::Vec};
 
     #[test]
     fn rfc_md5_test_suite() {
@@ -176,4 +473,238 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn sine_table() {
+        for (i, &value) in K.iter().enumerate() {
+            let expected = (2_u64.pow(32) as f64 * ((i + 1) as f64).sin().abs()) as u32;
+            assert_eq!(expected, value, "Wrong constant K[{}]", i);
+        }
+    }
+
+    #[test]
+    fn padding_block_boundaries() {
+        let test_cases: &[(usize, &str)] = &[
+            (55, "ef1772b6dff9a122358552954ad0df65"),
+            (56, "3b0c8ac703f828b04c6c197006d17218"),
+            (57, "652b906d60af96844ebd21b674f35e93"),
+            (63, "b06521f39153d618550606be297466d5"),
+            (64, "014842d480b571495a4a0363793f7367"),
+            (65, "c743a45e0d2e6a95cb859adae0248435"),
+            (119, "8a7bd0732ed6a28ce75f6dabc90e1613"),
+            (120, "5f61c0ccad4cac44c75ff505e1f1e537"),
+            (128, "e510683b3f5ffe4093d021808bc6ff70"),
+        ];
+
+        for &(len, expected) in test_cases {
+            assert_eq!(
+                expected,
+                digest_bytes(&vec![b'a'; len]).to_string(),
+                "Failed input length: {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let expected = digest_bytes(&data);
+
+        for chunk_len in 1..=130 {
+            let mut hasher = Md5::new();
+
+            for chunk in data.chunks(chunk_len) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(
+                expected,
+                hasher.finalize(),
+                "Failed chunk length: {}",
+                chunk_len
+            );
+        }
+    }
+
+    #[test]
+    fn bit_oriented_messages() {
+        // Computed with an independent reference implementation padding the
+        // bit string as RFC 1321 section 3.1 describes.
+        let ones = [0xffu8; 57];
+        let test_cases: &[(&[u8], u64, &str)] = &[
+            (b"", 0, "d41d8cd98f00b204e9800998ecf8427e"),
+            (&[0x80], 1, "7e663710ae2348bf0deaca2c79311eae"),
+            (&[0x00], 1, "1da635b1430f171c657206fd69fee0e8"),
+            (&[0xa0], 3, "0d3b29adf592b5d31afe94d88cc85fe9"),
+            (&[0x98], 5, "e0ce190aabc2e4aa602238ca5b81dd6d"),
+            (&[0x5e], 7, "1371f050e80b560e19c75811539a6d4b"),
+            (b"abc\xc0", 26, "ae5eb2a9c35a5586d911376eaf5a892b"),
+            (&ones[..56], 447, "32d0e1afdeb5c6f29ecb0ea0dc12c906"),
+            (&ones[..56], 448, "74444b7e7b01632f3277365c8ca35ec2"),
+            (&ones, 449, "cabc317058bc3e598e84e5f9bfaf2e8c"),
+            (&[0xaa; 64], 511, "848d9bdeb8c6fc9b1d5bcfad675f170c"),
+        ];
+
+        for &(input, bit_len, expected) in test_cases {
+            assert_eq!(
+                expected,
+                digest_bits(input, bit_len).to_string(),
+                "Failed bit length: {}",
+                bit_len
+            );
+        }
+    }
+
+    #[test]
+    fn bit_updates_match_byte_updates() {
+        let data: Vec<u8> = (0..130).map(|i| (i * 37) as u8).collect();
+
+        for len in 0..data.len() {
+            assert_eq!(
+                digest_bytes(&data[..len]),
+                digest_bits(&data, len as u64 * 8)
+            );
+
+            let mut hasher = Md5::new();
+            hasher.update(&data[..len / 2]);
+            hasher.update(&data[len / 2..len]);
+            let masked = [&data[..len], &[data[len] & 0xe0]].concat();
+            assert_eq!(
+                digest_bits(&masked, len as u64 * 8 + 3),
+                hasher.finalize_bits(data[len], 3)
+            );
+        }
+
+        assert_eq!(digest_bits(&[0x5e], 7), digest_bits(&[0x5f], 7));
+    }
+
+    #[test]
+    #[should_panic(expected = "A partial byte has at most 7 bits")]
+    fn finalize_bits_takes_a_partial_byte() {
+        Md5::new().finalize_bits(0xff, 8);
+    }
+
+    #[test]
+    fn rfc_2202_hmac_md5_test_suite() {
+        let test_cases: &[(&[u8], &[u8], &str)] = &[
+            (&[0x0b; 16], b"Hi There", "9294727a3638bb1c13f48ef8158bfc9d"),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "750c783e6ab0b503eaa86e310a5db738",
+            ),
+            (&[0xaa; 16], &[0xdd; 50], "56be34521d144c88dbb8c733f0e8b3f6"),
+            (
+                &[
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+                    0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+                ],
+                &[0xcd; 50],
+                "697eaf0aca3a3aea3a75164746ffaa79",
+            ),
+            (
+                &[0x0c; 16],
+                b"Test With Truncation",
+                "56461ef2342edc00f9bab995690efd4c",
+            ),
+            (
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd",
+            ),
+            (
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data",
+                "6f630fad67cda0ee1fb1f562db3aa53e",
+            ),
+        ];
+
+        for &(key, data, expected) in test_cases {
+            let tag = hmac(key, data);
+            assert_eq!(expected, tag.to_string(), "Failed key: {:02x?}", key);
+
+            let mut verifier = HmacMd5::new(key);
+            verifier.update(data);
+            assert!(verifier.verify(&tag.0));
+        }
+    }
+
+    #[test]
+    fn hmac_verify_rejects_wrong_tags() {
+        let tag = hmac(b"key", b"message");
+
+        let mut truncated = tag.0.to_vec();
+        truncated.pop();
+        let mut tampered = tag.0;
+        tampered[15] ^= 1;
+
+        for wrong_tag in [&truncated[..], &tampered, b""] {
+            let mut verifier = HmacMd5::new(b"key");
+            verifier.update(b"message");
+            assert!(!verifier.verify(wrong_tag));
+        }
+    }
+
+    #[test]
+    fn digest_decodings() {
+        let empty = digest("");
+
+        assert_eq!(Ok(empty), "d41d8cd98f00b204e9800998ecf8427e".parse());
+        assert_eq!(Ok(empty), "D41D8CD98F00B204E9800998ECF8427E".parse());
+        assert_eq!(Ok(empty), Digest::from_base64("1B2M2Y8AsgTpgAmY7PhCfg=="));
+        assert_eq!(Ok(empty), Digest::from_base64url("1B2M2Y8AsgTpgAmY7PhCfg"));
+
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            "d41d8cd9".parse::<Digest>()
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidCharacter('x')),
+            "x41d8cd98f00b204e9800998ecf8427e".parse::<Digest>()
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            Digest::from_base64("1B2M2Y8A")
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidLength),
+            Digest::try_from(&[0u8; 15][..])
+        );
+        assert_eq!(&empty.0[..], empty.as_ref());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn digest_encodings_round_trip() {
+        let empty = digest("");
+        assert_eq!("1B2M2Y8AsgTpgAmY7PhCfg==", empty.to_base64());
+        assert_eq!("1B2M2Y8AsgTpgAmY7PhCfg", empty.to_base64url());
+
+        let digest = Digest([0xfb; 16]);
+        assert_eq!("+/v7+/v7+/v7+/v7+/v7+w==", digest.to_base64());
+        assert_eq!("-_v7-_v7-_v7-_v7-_v7-w", digest.to_base64url());
+        assert_eq!(Ok(digest), Digest::from_base64url(&digest.to_base64url()));
+    }
+
+    #[test]
+    fn constant_time_digest_comparison() {
+        let tag = hmac(b"key", b"message");
+        let mut tampered = tag;
+        tampered.0[0] ^= 0x80;
+
+        assert!(tag.ct_eq(&hmac(b"key", b"message")));
+        assert!(!tag.ct_eq(&tampered));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_uses_hex_in_json() {
+        let digest = digest("abc");
+        let json = serde_json::to_string(&digest).unwrap();
+
+        assert_eq!("\"900150983cd24fb0d6963f7d28e17f72\"", json);
+        assert_eq!(digest, serde_json::from_str::<Digest>(&json).unwrap());
+        assert!(serde_json::from_str::<Digest>("\"90015098\"").is_err());
+    }
 }
@@ -0,0 +1,222 @@
+//! Checkpointing of an unfinished [`Md5`] computation.
+//!
+//! The midstate is serialized into a fixed-size, versioned binary format so a
+//! hash of a large input can be resumed in another process:
+//!
+//! | Offset | Size | Content                                  |
+//! |--------|------|------------------------------------------|
+//! | 0      | 4    | magic `MD5S`                             |
+//! | 4      | 1    | format version, currently `1`            |
+//! | 5      | 16   | registers a, b, c, d as little-endian u32 |
+//! | 21     | 8    | processed byte count as little-endian u64 |
+//! | 29     | 1    | partial block length                     |
+//! | 30     | 64   | partial block, zero-filled after its end |
+
+use std::fmt;
+
+use hasher::{encoding, BlockBuffer};
+
+use crate::Md5;
+
+pub const MIDSTATE_SIZE: usize = 94;
+
+const MAGIC: &[u8; 4] = b"MD5S";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidstateError {
+    InvalidSize(usize),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    InvalidHex,
+    /// The partial block length does not match the processed byte count.
+    Inconsistent,
+}
+
+impl fmt::Display for MidstateError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MidstateError::InvalidSize(size) => write!(
+                formatter,
+                "Midstate must be {} bytes long, got {}",
+                MIDSTATE_SIZE, size
+            ),
+            MidstateError::InvalidMagic => write!(formatter, "Data is not an MD5 midstate"),
+            MidstateError::UnsupportedVersion(version) => {
+                write!(formatter, "Unsupported midstate version {}", version)
+            }
+            MidstateError::InvalidHex => write!(formatter, "Midstate is not a valid hex string"),
+            MidstateError::Inconsistent => write!(
+                formatter,
+                "Partial block length does not match the processed byte count"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MidstateError {}
+
+impl Md5 {
+    pub fn export(&self) -> [u8; MIDSTATE_SIZE] {
+        let mut bytes = [0u8; MIDSTATE_SIZE];
+
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4] = VERSION;
+
+        for (chunk, word) in bytes[5..21].chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        let buffered = self.buffer.buffered();
+        bytes[21..29].copy_from_slice(&(self.buffer.length() as u64).to_le_bytes());
+        bytes[29] = buffered.len() as u8;
+        bytes[30..30 + buffered.len()].copy_from_slice(buffered);
+
+        bytes
+    }
+
+    pub fn export_hex(&self) -> String {
+        self.export()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn resume(bytes: &[u8]) -> Result<Md5, MidstateError> {
+        if bytes.len() != MIDSTATE_SIZE {
+            return Err(MidstateError::InvalidSize(bytes.len()));
+        }
+
+        if &bytes[..4] != MAGIC {
+            return Err(MidstateError::InvalidMagic);
+        }
+
+        if bytes[4] != VERSION {
+            return Err(MidstateError::UnsupportedVersion(bytes[4]));
+        }
+
+        let mut state = [0u32; 4];
+
+        for (word, chunk) in state.iter_mut().zip(bytes[5..21].chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let length = u64::from_le_bytes(bytes[21..29].try_into().unwrap());
+        let buffer_len = bytes[29] as usize;
+
+        if buffer_len as u64 != length % 64 || bytes[30 + buffer_len..].iter().any(|&b| b != 0) {
+            return Err(MidstateError::Inconsistent);
+        }
+
+        Ok(Md5 {
+            state,
+            buffer: BlockBuffer::from_parts(length as u128, &bytes[30..30 + buffer_len]),
+        })
+    }
+
+    pub fn resume_hex(hex: &str) -> Result<Md5, MidstateError> {
+        if hex.len() != 2 * MIDSTATE_SIZE {
+            return Err(MidstateError::InvalidSize(hex.len() / 2));
+        }
+
+        let mut bytes = [0u8; MIDSTATE_SIZE];
+        encoding::decode_hex(hex, &mut bytes).map_err(|_| MidstateError::InvalidHex)?;
+
+        Md5::resume(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_bytes;
+
+    #[test]
+    fn resumed_hash_matches_uninterrupted_one() {
+        let data: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let expected = digest_bytes(&data);
+
+        for split in 0..data.len() {
+            let mut hasher = Md5::new();
+            hasher.update(&data[..split]);
+
+            let mut resumed = Md5::resume(&hasher.export()).unwrap();
+            resumed.update(&data[split..]);
+            assert_eq!(expected, resumed.finalize(), "Failed split: {}", split);
+
+            let mut resumed = Md5::resume_hex(&hasher.export_hex()).unwrap();
+            resumed.update(&data[split..]);
+            assert_eq!(expected, resumed.finalize(), "Failed hex split: {}", split);
+        }
+    }
+
+    #[test]
+    fn format_is_stable() {
+        let mut hasher = Md5::new();
+        hasher.update(b"abc");
+
+        assert_eq!(
+            concat!(
+                "4d443553",
+                "01",
+                "0123456789abcdeffedcba9876543210",
+                "0300000000000000",
+                "03",
+                "616263",
+            ),
+            &hasher.export_hex()[..2 * 33]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_midstates() {
+        let mut hasher = Md5::new();
+        hasher.update(b"abc");
+        let bytes = hasher.export();
+
+        assert_eq!(
+            Err(MidstateError::InvalidSize(10)),
+            Md5::resume(&bytes[..10]).map(|_| ())
+        );
+
+        let mut wrong_magic = bytes;
+        wrong_magic[0] = b'X';
+        assert_eq!(
+            Err(MidstateError::InvalidMagic),
+            Md5::resume(&wrong_magic).map(|_| ())
+        );
+
+        let mut wrong_version = bytes;
+        wrong_version[4] = 2;
+        assert_eq!(
+            Err(MidstateError::UnsupportedVersion(2)),
+            Md5::resume(&wrong_version).map(|_| ())
+        );
+
+        let mut wrong_length = bytes;
+        wrong_length[21] = 4;
+        assert_eq!(
+            Err(MidstateError::Inconsistent),
+            Md5::resume(&wrong_length).map(|_| ())
+        );
+
+        let hex = hasher.export_hex();
+        for malformed in [
+            hex.replace('4', "g"),
+            format!("+{}", &hex[1..]),
+            format!("é{}", &hex[2..]),
+        ] {
+            assert_eq!(
+                Err(MidstateError::InvalidHex),
+                Md5::resume_hex(&malformed).map(|_| ()),
+                "Hex: {}",
+                malformed
+            );
+        }
+
+        assert_eq!(
+            Err(MidstateError::InvalidSize(MIDSTATE_SIZE - 1)),
+            Md5::resume_hex(&hex[2..]).map(|_| ())
+        );
+    }
+}
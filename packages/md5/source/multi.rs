@@ -0,0 +1,359 @@
+//! Multi-buffer hashing: several independent messages are compressed side by
+//! side, one message per SIMD lane. AVX2 handles 8 lanes and SSE2 handles 4,
+//! both picked at runtime, with a portable scalar fallback for everything else.
+
+use crate::{compress_words, Digest, INITIAL_STATE};
+
+fn padded_block_count(len: usize) -> usize {
+    (len + 8) / 64 + 1
+}
+
+fn padded_block(input: &[u8], index: usize) -> [u8; 64] {
+    let mut block = [0u8; 64];
+    let start = index * 64;
+
+    if start < input.len() {
+        let chunk = &input[start..input.len().min(start + 64)];
+        block[..chunk.len()].copy_from_slice(chunk);
+    }
+
+    if (start..start + 64).contains(&input.len()) {
+        block[input.len() - start] = 0x80;
+    }
+
+    if index + 1 == padded_block_count(input.len()) {
+        let data_len_bits = (input.len() as u64).wrapping_mul(8);
+        block[56..].copy_from_slice(&data_len_bits.to_le_bytes());
+    }
+
+    block
+}
+
+/// Feeds the padded blocks of every input through `compress_lanes`, keeping
+/// the state word-major (`state[word][lane]`) so vector backends can load a
+/// whole row at once. Lanes whose message is exhausted keep compressing zero
+/// blocks; their digest has already been captured by then.
+fn digest_lanes<const N: usize, F>(inputs: [&[u8]; N], mut compress_lanes: F) -> [Digest; N]
+where
+    F: FnMut(&mut [[u32; N]; 4], &[[u32; N]; 16]),
+{
+    let block_counts = inputs.map(|input| padded_block_count(input.len()));
+    let max_block_count = block_counts.iter().copied().max().unwrap_or(0);
+
+    let mut state = INITIAL_STATE.map(|word| [word; N]);
+    let mut digests = [Digest([0u8; 16]); N];
+
+    for index in 0..max_block_count {
+        let mut x = [[0u32; N]; 16];
+
+        for (lane, input) in inputs.iter().enumerate() {
+            if index >= block_counts[lane] {
+                continue;
+            }
+
+            let block = padded_block(input, index);
+
+            for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+                word[lane] = u32::from_le_bytes(bytes.try_into().unwrap());
+            }
+        }
+
+        compress_lanes(&mut state, &x);
+
+        for (lane, digest) in digests.iter_mut().enumerate() {
+            if index + 1 == block_counts[lane] {
+                *digest = Digest::from(state.map(|word| word[lane]));
+            }
+        }
+    }
+
+    digests
+}
+
+fn compress_scalar<const N: usize>(state: &mut [[u32; N]; 4], x: &[[u32; N]; 16]) {
+    for lane in 0..N {
+        let mut lane_state = state.map(|word| word[lane]);
+        compress_words(&mut lane_state, &x.map(|word| word[lane]));
+
+        for (word, value) in state.iter_mut().zip(lane_state) {
+            word[lane] = value;
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use crate::K;
+
+    #[target_feature(enable = "sse2")]
+    #[inline]
+    unsafe fn f(x: __m128i, y: __m128i, z: __m128i) -> __m128i {
+        _mm_or_si128(_mm_and_si128(x, y), _mm_andnot_si128(x, z))
+    }
+
+    #[target_feature(enable = "sse2")]
+    #[inline]
+    unsafe fn g(x: __m128i, y: __m128i, z: __m128i) -> __m128i {
+        _mm_or_si128(_mm_and_si128(x, z), _mm_andnot_si128(z, y))
+    }
+
+    #[target_feature(enable = "sse2")]
+    #[inline]
+    unsafe fn h(x: __m128i, y: __m128i, z: __m128i) -> __m128i {
+        _mm_xor_si128(_mm_xor_si128(x, y), z)
+    }
+
+    #[target_feature(enable = "sse2")]
+    #[inline]
+    unsafe fn i(x: __m128i, y: __m128i, z: __m128i) -> __m128i {
+        _mm_xor_si128(y, _mm_or_si128(x, _mm_xor_si128(z, _mm_set1_epi32(-1))))
+    }
+
+    macro_rules! step {
+        ($func:ident, $a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $k:expr, $s:literal) => {
+            let sum = _mm_add_epi32(
+                _mm_add_epi32($a, $func($b, $c, $d)),
+                _mm_add_epi32($x, _mm_set1_epi32($k as i32)),
+            );
+            $a = _mm_add_epi32(
+                $b,
+                _mm_or_si128(
+                    _mm_slli_epi32::<$s>(sum),
+                    _mm_srli_epi32::<{ 32 - $s }>(sum),
+                ),
+            );
+        };
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn compress(state: &mut [[u32; 4]; 4], x: &[[u32; 4]; 16]) {
+        let mut w = [_mm_setzero_si128(); 16];
+
+        for (w, x) in w.iter_mut().zip(x) {
+            *w = _mm_loadu_si128(x.as_ptr().cast());
+        }
+
+        let mut a = _mm_loadu_si128(state[0].as_ptr().cast());
+        let mut b = _mm_loadu_si128(state[1].as_ptr().cast());
+        let mut c = _mm_loadu_si128(state[2].as_ptr().cast());
+        let mut d = _mm_loadu_si128(state[3].as_ptr().cast());
+
+        md5_steps!(step, a, b, c, d, w);
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            let sum = _mm_add_epi32(_mm_loadu_si128(word.as_ptr().cast()), value);
+            _mm_storeu_si128(word.as_mut_ptr().cast(), sum);
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use crate::K;
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn f(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_or_si256(_mm256_and_si256(x, y), _mm256_andnot_si256(x, z))
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn g(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_or_si256(_mm256_and_si256(x, z), _mm256_andnot_si256(z, y))
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn h(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_xor_si256(_mm256_xor_si256(x, y), z)
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn i(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_xor_si256(
+            y,
+            _mm256_or_si256(x, _mm256_xor_si256(z, _mm256_set1_epi32(-1))),
+        )
+    }
+
+    macro_rules! step {
+        ($func:ident, $a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $k:expr, $s:literal) => {
+            let sum = _mm256_add_epi32(
+                _mm256_add_epi32($a, $func($b, $c, $d)),
+                _mm256_add_epi32($x, _mm256_set1_epi32($k as i32)),
+            );
+            $a = _mm256_add_epi32(
+                $b,
+                _mm256_or_si256(
+                    _mm256_slli_epi32::<$s>(sum),
+                    _mm256_srli_epi32::<{ 32 - $s }>(sum),
+                ),
+            );
+        };
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn compress(state: &mut [[u32; 8]; 4], x: &[[u32; 8]; 16]) {
+        let mut w = [_mm256_setzero_si256(); 16];
+
+        for (w, x) in w.iter_mut().zip(x) {
+            *w = _mm256_loadu_si256(x.as_ptr().cast());
+        }
+
+        let mut a = _mm256_loadu_si256(state[0].as_ptr().cast());
+        let mut b = _mm256_loadu_si256(state[1].as_ptr().cast());
+        let mut c = _mm256_loadu_si256(state[2].as_ptr().cast());
+        let mut d = _mm256_loadu_si256(state[3].as_ptr().cast());
+
+        md5_steps!(step, a, b, c, d, w);
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            let sum = _mm256_add_epi32(_mm256_loadu_si256(word.as_ptr().cast()), value);
+            _mm256_storeu_si256(word.as_mut_ptr().cast(), sum);
+        }
+    }
+}
+
+/// Hashes 4 independent messages at once, using SSE2 when available.
+pub fn digest_x4(inputs: [&[u8]; 4]) -> [Digest; 4] {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("sse2") {
+        return digest_lanes(inputs, |state, x| unsafe { sse2::compress(state, x) });
+    }
+
+    digest_lanes(inputs, compress_scalar)
+}
+
+/// Hashes 8 independent messages at once, using AVX2 when available and two
+/// 4-lane passes otherwise.
+pub fn digest_x8(inputs: [&[u8]; 8]) -> [Digest; 8] {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("avx2") {
+        return digest_lanes(inputs, |state, x| unsafe { avx2::compress(state, x) });
+    }
+
+    let [a, b, c, d, e, f, g, h] = inputs;
+    let [a, b, c, d] = digest_x4([a, b, c, d]);
+    let [e, f, g, h] = digest_x4([e, f, g, h]);
+
+    [a, b, c, d, e, f, g, h]
+}
+
+/// Hashes any number of messages, 8 lanes at a time. Digests are returned in
+/// the order of `inputs`.
+pub fn digest_many(inputs: &[&[u8]]) -> Vec<Digest> {
+    let mut digests = Vec::with_capacity(inputs.len());
+
+    for chunk in inputs.chunks(8) {
+        let mut lanes: [&[u8]; 8] = [&[]; 8];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+
+        digests.extend_from_slice(&digest_x8(lanes)[..chunk.len()]);
+    }
+
+    digests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_bytes;
+
+    fn messages() -> Vec<Vec<u8>> {
+        (0..200)
+            .map(|len| (0..len).map(|i| (i * 31 + len) as u8).collect())
+            .collect()
+    }
+
+    #[test]
+    fn padded_blocks_match_single_buffer_padding() {
+        for message in messages() {
+            let mut state = INITIAL_STATE;
+
+            for index in 0..padded_block_count(message.len()) {
+                crate::compress(&mut state, &padded_block(&message, index));
+            }
+
+            assert_eq!(digest_bytes(&message), Digest::from(state));
+        }
+    }
+
+    #[test]
+    fn scalar_lanes() {
+        let messages = messages();
+
+        for window in messages.windows(4) {
+            let inputs = [&window[0][..], &window[1], &window[2], &window[3]];
+            let digests = digest_lanes(inputs, compress_scalar);
+
+            for (input, digest) in inputs.iter().zip(digests) {
+                assert_eq!(digest_bytes(input), digest);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn sse2_lanes() {
+        if !is_x86_feature_detected!("sse2") {
+            return;
+        }
+
+        let messages = messages();
+
+        for window in messages.windows(4) {
+            let inputs = [&window[0][..], &window[1], &window[2], &window[3]];
+            let digests = digest_lanes(inputs, |state, x| unsafe { sse2::compress(state, x) });
+
+            for (input, digest) in inputs.iter().zip(digests) {
+                assert_eq!(digest_bytes(input), digest);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn avx2_lanes() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let messages = messages();
+
+        for window in messages.windows(8) {
+            let inputs: [&[u8]; 8] = std::array::from_fn(|lane| &window[lane][..]);
+            let digests = digest_lanes(inputs, |state, x| unsafe { avx2::compress(state, x) });
+
+            for (input, digest) in inputs.iter().zip(digests) {
+                assert_eq!(digest_bytes(input), digest);
+            }
+        }
+    }
+
+    #[test]
+    fn many_messages_of_mixed_lengths() {
+        let mut messages = messages();
+        messages.reverse();
+        messages.push(vec![0u8; 1000]);
+
+        let inputs: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+        let digests = digest_many(&inputs);
+
+        assert_eq!(inputs.len(), digests.len());
+        for (input, digest) in inputs.iter().zip(digests) {
+            assert_eq!(digest_bytes(input), digest);
+        }
+    }
+}
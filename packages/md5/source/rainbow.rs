@@ -0,0 +1,687 @@
+//! Rainbow tables, Oechslin's time-memory trade-off for inverting MD5 over a
+//! bounded plaintext space.
+//!
+//! Plaintexts are numbered: all strings of `min_length` characters of the
+//! charset come first, then those one character longer and so on, the first
+//! character being the least significant digit. A chain starts at a plaintext
+//! index and `chain_length` times hashes the plaintext and maps the digest back
+//! to an index with the reduction function of the column. Only the start and
+//! end of every chain are stored, sorted by end so a lookup is a binary search.
+//! Chains ending at the same index have merged, only one of them is kept.
+//!
+//! Table file format, integers are little-endian:
+//!
+//! | Offset | Size | Content                                        |
+//! |--------|------|------------------------------------------------|
+//! | 0      | 4    | magic `MD5R`                                   |
+//! | 4      | 1    | format version, currently `1`                  |
+//! | 5      | 1    | reduction, `0` modulo and `1` mixed            |
+//! | 6      | 1    | minimum plaintext length                       |
+//! | 7      | 1    | maximum plaintext length                       |
+//! | 8      | 4    | chain length                                   |
+//! | 12     | 4    | table index                                    |
+//! | 16     | 8    | number of generated chains                     |
+//! | 24     | 8    | number of stored chains                        |
+//! | 32     | 2    | charset length `n`                             |
+//! | 34     | n    | charset                                        |
+//! | 34 + n | ...  | start then end of every chain, sorted by end   |
+//!
+//! Chain indices use the fewest bytes that can hold the largest plaintext
+//! index, 5 bytes per index for 7 lowercase letters.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::multi::digest_x8;
+use crate::{digest_bytes, Digest};
+
+const MAGIC: &[u8; 4] = b"MD5R";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 34;
+const LANES: usize = 8;
+const CHAINS_PER_TASK: u64 = 1 << 12;
+
+#[derive(Debug)]
+pub enum RainbowError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    UnknownReduction,
+    InvalidParameters(&'static str),
+    /// The file ends before the last chain.
+    Truncated,
+    /// The chains are not sorted by end.
+    Unsorted,
+    /// A chain starts or ends outside the plaintext space.
+    IndexOutOfRange,
+}
+
+impl fmt::Display for RainbowError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RainbowError::Io(error) => write!(formatter, "{}", error),
+            RainbowError::InvalidMagic => write!(formatter, "Data is not an MD5 rainbow table"),
+            RainbowError::UnsupportedVersion(version) => {
+                write!(formatter, "Unsupported rainbow table version {}", version)
+            }
+            RainbowError::UnknownReduction => write!(formatter, "Unknown reduction function"),
+            RainbowError::InvalidParameters(reason) => write!(formatter, "{}", reason),
+            RainbowError::Truncated => write!(formatter, "Rainbow table is truncated"),
+            RainbowError::Unsorted => write!(formatter, "Rainbow table chains are not sorted"),
+            RainbowError::IndexOutOfRange => {
+                write!(
+                    formatter,
+                    "Rainbow table chain is outside the plaintext space"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RainbowError {}
+
+impl From<io::Error> for RainbowError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => RainbowError::Truncated,
+            _ => RainbowError::Io(error),
+        }
+    }
+}
+
+/// Maps a digest and a column to a plaintext index. The column and the table
+/// index are both mixed in, so every column of every table uses a different
+/// function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    /// The first 64 bits of the digest plus the column, modulo the space size.
+    Modulo,
+    /// The first 64 bits of the digest xored with the column and scrambled by
+    /// the SplitMix64 finalizer, modulo the space size.
+    Mixed,
+}
+
+impl Reduction {
+    fn id(self) -> u8 {
+        match self {
+            Reduction::Modulo => 0,
+            Reduction::Mixed => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, RainbowError> {
+        match id {
+            0 => Ok(Reduction::Modulo),
+            1 => Ok(Reduction::Mixed),
+            _ => Err(RainbowError::UnknownReduction),
+        }
+    }
+}
+
+impl FromStr for Reduction {
+    type Err = RainbowError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "modulo" => Ok(Reduction::Modulo),
+            "mixed" => Ok(Reduction::Mixed),
+            _ => Err(RainbowError::UnknownReduction),
+        }
+    }
+}
+
+impl fmt::Display for Reduction {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reduction::Modulo => write!(formatter, "modulo"),
+            Reduction::Mixed => write!(formatter, "mixed"),
+        }
+    }
+}
+
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+/// The plaintexts covered by a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Space {
+    charset: Vec<u8>,
+    min_length: usize,
+    max_length: usize,
+    /// Index of the first plaintext of every length, then the space size.
+    offsets: Vec<u64>,
+}
+
+impl Space {
+    pub fn new(charset: &[u8], min_length: usize, max_length: usize) -> Result<Self, RainbowError> {
+        let mut sorted = charset.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        if charset.is_empty() || sorted.len() != charset.len() {
+            return Err(RainbowError::InvalidParameters(
+                "Charset must be non-empty without repeated characters",
+            ));
+        }
+
+        if min_length == 0 || min_length > max_length || max_length > u8::MAX as usize {
+            return Err(RainbowError::InvalidParameters(
+                "Lengths must satisfy 1 <= minimum <= maximum <= 255",
+            ));
+        }
+
+        let mut offsets = vec![0u64];
+        let mut count = (charset.len() as u64).checked_pow(min_length as u32);
+
+        for _ in min_length..=max_length {
+            let size = count
+                .and_then(|count| offsets.last().unwrap().checked_add(count))
+                .ok_or(RainbowError::InvalidParameters(
+                    "Plaintext space does not fit in 64 bits",
+                ))?;
+
+            offsets.push(size);
+            count = count.and_then(|count| count.checked_mul(charset.len() as u64));
+        }
+
+        Ok(Space {
+            charset: charset.to_vec(),
+            min_length,
+            max_length,
+            offsets,
+        })
+    }
+
+    pub fn charset(&self) -> &[u8] {
+        &self.charset
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Number of plaintexts in the space.
+    pub fn size(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    /// Writes the plaintext number `index` into `output`, replacing its
+    /// content.
+    pub fn plaintext(&self, index: u64, output: &mut Vec<u8>) {
+        let length = self.offsets[1..]
+            .iter()
+            .position(|&end| index < end)
+            .expect("Index is inside the space");
+
+        let base = self.charset.len() as u64;
+        let mut rest = index - self.offsets[length];
+
+        output.clear();
+        for _ in 0..self.min_length + length {
+            output.push(self.charset[(rest % base) as usize]);
+            rest /= base;
+        }
+    }
+
+    /// Bytes needed to store any plaintext index.
+    fn index_size(&self) -> usize {
+        let bits = 64 - (self.size() - 1).leading_zeros() as usize;
+        bits.div_ceil(8).max(1)
+    }
+}
+
+/// Everything that defines the chains of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameters {
+    pub space: Space,
+    pub chain_length: u32,
+    pub reduction: Reduction,
+    /// Tables of the same space only differ by their index, which changes
+    /// their reduction functions.
+    pub table_index: u32,
+}
+
+impl Parameters {
+    fn reduce(&self, digest: &Digest, column: u32) -> u64 {
+        let hash = u64::from_le_bytes(digest.0[..8].try_into().unwrap());
+        let salt = (self.table_index as u64) << 32 | column as u64;
+
+        let value = match self.reduction {
+            Reduction::Modulo => hash.wrapping_add(salt),
+            Reduction::Mixed => mix(hash ^ salt),
+        };
+
+        value % self.space.size()
+    }
+
+    /// Moves every lane from its column in `columns` to the end of the chain,
+    /// hashing the 8 lanes side by side.
+    fn walk_to_end(&self, points: &mut [u64; LANES], columns: [u32; LANES]) {
+        let mut plaintexts: [Vec<u8>; LANES] = Default::default();
+        let first = columns.iter().copied().min().unwrap();
+
+        for column in first..self.chain_length {
+            for (lane, plaintext) in plaintexts.iter_mut().enumerate() {
+                match columns[lane] <= column {
+                    true => self.space.plaintext(points[lane], plaintext),
+                    false => plaintext.clear(),
+                }
+            }
+
+            let digests = digest_x8(std::array::from_fn(|lane| plaintexts[lane].as_slice()));
+
+            for lane in 0..LANES {
+                if columns[lane] <= column {
+                    points[lane] = self.reduce(&digests[lane], column);
+                }
+            }
+        }
+    }
+
+    /// Walks a single chain from its start to `column` and returns the
+    /// plaintext found there.
+    fn plaintext_at(&self, start: u64, column: u32) -> Vec<u8> {
+        let mut plaintext = Vec::new();
+        let mut point = start;
+
+        for column in 0..column {
+            self.space.plaintext(point, &mut plaintext);
+            point = self.reduce(&digest_bytes(&plaintext), column);
+        }
+
+        self.space.plaintext(point, &mut plaintext);
+        plaintext
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Chain {
+    start: u64,
+    end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    parameters: Parameters,
+    generated: u64,
+    chains: Vec<Chain>,
+}
+
+impl Table {
+    /// Computes `chain_count` chains, starting at the first `chain_count`
+    /// plaintext indices, on `threads` worker threads.
+    pub fn generate(
+        parameters: Parameters,
+        chain_count: u64,
+        threads: usize,
+    ) -> Result<Table, RainbowError> {
+        if chain_count == 0 || chain_count > parameters.space.size() {
+            return Err(RainbowError::InvalidParameters(
+                "Chain count must be between 1 and the size of the plaintext space",
+            ));
+        }
+
+        if parameters.chain_length == 0 {
+            return Err(RainbowError::InvalidParameters(
+                "Chain length must be at least 1",
+            ));
+        }
+
+        let next_task = AtomicU64::new(0);
+        let chains = Mutex::new(Vec::with_capacity(chain_count as usize));
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| loop {
+                    let first = next_task.fetch_add(CHAINS_PER_TASK, Ordering::Relaxed);
+                    if first >= chain_count {
+                        break;
+                    }
+
+                    let last = (first + CHAINS_PER_TASK).min(chain_count);
+                    let mut computed = Vec::with_capacity((last - first) as usize);
+
+                    for group in (first..last).step_by(LANES) {
+                        let starts: [u64; LANES] =
+                            std::array::from_fn(|lane| (group + lane as u64).min(last - 1));
+                        let mut ends = starts;
+                        parameters.walk_to_end(&mut ends, [0; LANES]);
+
+                        let count = (last - group).min(LANES as u64) as usize;
+                        computed.extend((0..count).map(|lane| Chain {
+                            start: starts[lane],
+                            end: ends[lane],
+                        }));
+                    }
+
+                    chains.lock().unwrap().extend(computed);
+                });
+            }
+        });
+
+        let mut chains = chains.into_inner().unwrap();
+        chains.sort_unstable_by_key(|chain| (chain.end, chain.start));
+        chains.dedup_by_key(|chain| chain.end);
+
+        Ok(Table {
+            parameters,
+            generated: chain_count,
+            chains,
+        })
+    }
+
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// Number of chains computed, including the merged ones that were dropped.
+    pub fn generated_chains(&self) -> u64 {
+        self.generated
+    }
+
+    pub fn stored_chains(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Estimated probability that the plaintext of a random digest of the
+    /// space is in the table. Stored chains never merge, so every column
+    /// holds `m` distinct points out of `N` and a digest is missed by all
+    /// `t` columns with probability `(1 - m / N)^t`.
+    pub fn success_probability(&self) -> f64 {
+        let coverage = self.chains.len() as f64 / self.parameters.space.size() as f64;
+
+        1.0 - (1.0 - coverage).powf(self.parameters.chain_length as f64)
+    }
+
+    /// Searches the plaintext of `digest`, trying the columns from the last
+    /// one since they need the shortest walks.
+    pub fn lookup(&self, digest: &Digest) -> Option<Vec<u8>> {
+        let parameters = &self.parameters;
+        let columns: Vec<u32> = (0..parameters.chain_length).rev().collect();
+
+        for group in columns.chunks(LANES) {
+            let mut points = [0u64; LANES];
+            let mut next_columns = [parameters.chain_length; LANES];
+
+            for (lane, &column) in group.iter().enumerate() {
+                points[lane] = parameters.reduce(digest, column);
+                next_columns[lane] = column + 1;
+            }
+
+            parameters.walk_to_end(&mut points, next_columns);
+
+            for (lane, &column) in group.iter().enumerate() {
+                let Ok(found) = self
+                    .chains
+                    .binary_search_by_key(&points[lane], |chain| chain.end)
+                else {
+                    continue;
+                };
+
+                // The digest may only share the end of the chain, a false alarm.
+                let plaintext = parameters.plaintext_at(self.chains[found].start, column);
+                if digest_bytes(&plaintext) == *digest {
+                    return Some(plaintext);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), RainbowError> {
+        let parameters = &self.parameters;
+        let space = &parameters.space;
+        let mut header = Vec::with_capacity(HEADER_SIZE + space.charset.len());
+
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.push(parameters.reduction.id());
+        header.push(space.min_length as u8);
+        header.push(space.max_length as u8);
+        header.extend_from_slice(&parameters.chain_length.to_le_bytes());
+        header.extend_from_slice(&parameters.table_index.to_le_bytes());
+        header.extend_from_slice(&self.generated.to_le_bytes());
+        header.extend_from_slice(&(self.chains.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(space.charset.len() as u16).to_le_bytes());
+        header.extend_from_slice(&space.charset);
+        writer.write_all(&header)?;
+
+        let index_size = space.index_size();
+        let mut chunk = Vec::with_capacity(index_size * 2 * 4096);
+
+        for chains in self.chains.chunks(4096) {
+            chunk.clear();
+
+            for chain in chains {
+                chunk.extend_from_slice(&chain.start.to_le_bytes()[..index_size]);
+                chunk.extend_from_slice(&chain.end.to_le_bytes()[..index_size]);
+            }
+
+            writer.write_all(&chunk)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> Result<Table, RainbowError> {
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+
+        if &header[..4] != MAGIC {
+            return Err(RainbowError::InvalidMagic);
+        }
+
+        if header[4] != VERSION {
+            return Err(RainbowError::UnsupportedVersion(header[4]));
+        }
+
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+
+        let mut charset = vec![0u8; u16::from_le_bytes([header[32], header[33]]) as usize];
+        reader.read_exact(&mut charset)?;
+
+        let parameters = Parameters {
+            space: Space::new(&charset, header[6] as usize, header[7] as usize)?,
+            chain_length: u32_at(8),
+            reduction: Reduction::from_id(header[5])?,
+            table_index: u32_at(12),
+        };
+        let generated = u64_at(16);
+        let stored = u64_at(24);
+
+        if parameters.chain_length == 0 || stored > generated {
+            return Err(RainbowError::InvalidParameters(
+                "Inconsistent rainbow table header",
+            ));
+        }
+
+        let index_size = parameters.space.index_size();
+        let mut chains = Vec::new();
+        let mut entry = [0u8; 16];
+
+        for _ in 0..stored {
+            reader.read_exact(&mut entry[..index_size * 2])?;
+
+            let mut start = [0u8; 8];
+            let mut end = [0u8; 8];
+            start[..index_size].copy_from_slice(&entry[..index_size]);
+            end[..index_size].copy_from_slice(&entry[index_size..index_size * 2]);
+
+            let chain = Chain {
+                start: u64::from_le_bytes(start),
+                end: u64::from_le_bytes(end),
+            };
+
+            if chain.start >= parameters.space.size() || chain.end >= parameters.space.size() {
+                return Err(RainbowError::IndexOutOfRange);
+            }
+
+            chains.push(chain);
+        }
+
+        if chains.windows(2).any(|pair| pair[0].end >= pair[1].end) {
+            return Err(RainbowError::Unsorted);
+        }
+
+        Ok(Table {
+            parameters,
+            generated,
+            chains,
+        })
+    }
+}
+
+/// Probability that at least one of `tables` holds the plaintext of a
+/// random digest, the tables being independent.
+pub fn combined_success_probability(tables: &[Table]) -> f64 {
+    1.0 - tables
+        .iter()
+        .map(|table| 1.0 - table.success_probability())
+        .product::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(reduction: Reduction, table_index: u32) -> Parameters {
+        Parameters {
+            space: Space::new(b"abcdefghijklmnopqrstuvwxyz", 1, 4).unwrap(),
+            chain_length: 200,
+            reduction,
+            table_index,
+        }
+    }
+
+    #[test]
+    fn space_enumerates_every_plaintext_once() {
+        let space = Space::new(b"abc", 2, 3).unwrap();
+        let mut plaintext = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+
+        assert_eq!(9 + 27, space.size());
+
+        for index in 0..space.size() {
+            space.plaintext(index, &mut plaintext);
+            assert!((2..=3).contains(&plaintext.len()));
+            assert!(seen.insert(plaintext.clone()));
+        }
+
+        space.plaintext(0, &mut plaintext);
+        assert_eq!(b"aa", &plaintext[..]);
+        space.plaintext(10, &mut plaintext);
+        assert_eq!(b"baa", &plaintext[..]);
+
+        assert!(Space::new(b"aba", 1, 2).is_err());
+        assert!(Space::new(b"ab", 3, 2).is_err());
+        assert!(Space::new(b"0123456789", 1, 20).is_err());
+    }
+
+    #[test]
+    fn lookup_finds_points_of_the_chains() {
+        for reduction in [Reduction::Modulo, Reduction::Mixed] {
+            let table = Table::generate(parameters(reduction, 0), 2000, 4).unwrap();
+
+            for chain in table.chains.iter().step_by(97) {
+                for column in [0, 1, 57, 199] {
+                    let plaintext = table.parameters.plaintext_at(chain.start, column);
+                    let digest = digest_bytes(&plaintext);
+
+                    let found = table.lookup(&digest).expect("Point is in the table");
+                    assert_eq!(digest, digest_bytes(&found));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn success_rate_matches_estimate() {
+        let tables: Vec<Table> = (0..2)
+            .map(|index| Table::generate(parameters(Reduction::Mixed, index), 3000, 4).unwrap())
+            .collect();
+        let expected = combined_success_probability(&tables);
+
+        let space = &tables[0].parameters.space;
+        let mut plaintext = Vec::new();
+        let mut found = 0;
+        let samples = 300;
+
+        for sample in 0..samples {
+            space.plaintext(mix(sample) % space.size(), &mut plaintext);
+            let digest = digest_bytes(&plaintext);
+
+            if tables.iter().any(|table| table.lookup(&digest).is_some()) {
+                found += 1;
+            }
+        }
+
+        let rate = found as f64 / samples as f64;
+        assert!(expected > 0.3 && expected < 1.0, "Estimate: {}", expected);
+        assert!(
+            (rate - expected).abs() < 0.1,
+            "Rate {} for {}",
+            rate,
+            expected
+        );
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let table = Table::generate(parameters(Reduction::Modulo, 7), 500, 2).unwrap();
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+
+        assert_eq!(
+            HEADER_SIZE + 26 + table.stored_chains() * 2 * 3,
+            bytes.len()
+        );
+        assert_eq!(table, Table::read_from(&bytes[..]).unwrap());
+
+        assert!(matches!(
+            Table::read_from(&bytes[..bytes.len() - 1]),
+            Err(RainbowError::Truncated)
+        ));
+
+        bytes[0] = b'X';
+        assert!(matches!(
+            Table::read_from(&bytes[..]),
+            Err(RainbowError::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn rejects_chains_outside_the_space() {
+        let table = Table::generate(parameters(Reduction::Modulo, 0), 100, 1).unwrap();
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+
+        // Every index takes 3 bytes for the 475254 plaintexts of the space
+        let first_start = HEADER_SIZE + 26;
+        let last_end = bytes.len() - 3;
+
+        for offset in [first_start, last_end] {
+            let mut corrupted = bytes.clone();
+            corrupted[offset..offset + 3].copy_from_slice(&[0xff; 3]);
+
+            assert!(matches!(
+                Table::read_from(&corrupted[..]),
+                Err(RainbowError::IndexOutOfRange)
+            ));
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! HMAC-based one-time passwords, HOTP as specified in RFC 4226 and its
+//! time-based variant TOTP from RFC 6238.
+//!
+//! A code is the HMAC of a big-endian 64-bit counter, dynamically truncated to
+//! 31 bits and reduced to the configured number of decimal digits. TOTP takes
+//! the counter from the Unix time divided by the time step.
+
+use std::fmt;
+use std::iter;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hasher::{Hasher, Hmac};
+
+pub mod uri;
+
+pub use uri::{Generator, OtpAuth, UriError};
+
+/// Largest number of digits, the truncated HMAC is below `2^31`.
+pub const MAX_DIGITS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Parses the `algorithm` parameter of `otpauth://` URIs, in either case.
+impl FromStr for Algorithm {
+    type Err = UriError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Ok(Algorithm::Sha1),
+            "SHA256" => Ok(Algorithm::Sha256),
+            "SHA512" => Ok(Algorithm::Sha512),
+            _ => Err(UriError::InvalidParameter("algorithm")),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Algorithm::Sha1 => write!(formatter, "SHA1"),
+            Algorithm::Sha256 => write!(formatter, "SHA256"),
+            Algorithm::Sha512 => write!(formatter, "SHA512"),
+        }
+    }
+}
+
+fn truncate<H: Hasher>(secret: &[u8], counter: u64) -> u32 {
+    let mac = Hmac::<H>::mac(secret, &counter.to_be_bytes());
+    let mac = mac.as_ref();
+
+    let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+    u32::from_be_bytes(mac[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff
+}
+
+/// Counter-based generator.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Hotp {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+}
+
+/// Leaves the secret out, so logging a generator does not leak the key.
+impl fmt::Debug for Hotp {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Hotp")
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .finish()
+    }
+}
+
+impl Hotp {
+    /// Generator of 6 digit HMAC-SHA-1 codes, the RFC 4226 defaults.
+    pub fn new(secret: &[u8]) -> Self {
+        Hotp {
+            secret: secret.to_vec(),
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// When `digits` is 0 or above [`MAX_DIGITS`].
+    pub fn with_digits(mut self, digits: u32) -> Self {
+        assert!(
+            (1..=MAX_DIGITS).contains(&digits),
+            "Codes have 1 to {} digits",
+            MAX_DIGITS
+        );
+
+        self.digits = digits;
+        self
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    /// Code of `counter`, zero-padded to the number of digits.
+    pub fn generate(&self, counter: u64) -> String {
+        let value = match self.algorithm {
+            Algorithm::Sha1 => truncate::<sha::Sha1>(&self.secret, counter),
+            Algorithm::Sha256 => truncate::<sha::Sha256>(&self.secret, counter),
+            Algorithm::Sha512 => truncate::<sha::Sha512>(&self.secret, counter),
+        };
+
+        let code = value as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Looks `code` up among the counters `counter..=counter + look_ahead`,
+    /// comparing in constant time. Returns the matching counter, the next
+    /// expected one being the counter after it.
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+        (counter..=counter.saturating_add(look_ahead))
+            .find(|&candidate| hasher::ct_eq(self.generate(candidate).as_bytes(), code.as_bytes()))
+    }
+}
+
+/// Time-based generator.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Totp {
+    hotp: Hotp,
+    step: u64,
+    start: u64,
+}
+
+impl fmt::Debug for Totp {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Totp")
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.hotp.algorithm)
+            .field("digits", &self.hotp.digits)
+            .field("step", &self.step)
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl Totp {
+    /// Generator of 6 digit HMAC-SHA-1 codes changing every 30 seconds from
+    /// the Unix epoch, the RFC 6238 defaults.
+    pub fn new(secret: &[u8]) -> Self {
+        Totp {
+            hotp: Hotp::new(secret),
+            step: 30,
+            start: 0,
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.hotp = self.hotp.with_algorithm(algorithm);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// When `digits` is 0 or above [`MAX_DIGITS`].
+    pub fn with_digits(mut self, digits: u32) -> Self {
+        self.hotp = self.hotp.with_digits(digits);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// When `step` is 0.
+    pub fn with_step(mut self, step: u64) -> Self {
+        assert!(step > 0, "Time step must be at least one second");
+
+        self.step = step;
+        self
+    }
+
+    /// Unix time of the first step, `T0` in the RFC.
+    pub fn with_start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn hotp(&self) -> &Hotp {
+        &self.hotp
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Step number of `unix_time`, the HOTP counter.
+    pub fn counter(&self, unix_time: u64) -> u64 {
+        unix_time.saturating_sub(self.start) / self.step
+    }
+
+    pub fn generate(&self, unix_time: u64) -> String {
+        self.hotp.generate(self.counter(unix_time))
+    }
+
+    pub fn generate_now(&self) -> String {
+        self.generate(unix_time_now())
+    }
+
+    /// Accepts `code` from up to `drift` steps before or after the one of
+    /// `unix_time`, to tolerate clock skew and transmission delay. Returns by
+    /// how many steps the code was off, the current step being tried first.
+    pub fn verify(&self, code: &str, unix_time: u64, drift: u64) -> Option<i64> {
+        let counter = self.counter(unix_time);
+
+        let offsets = (1..=drift as i64).flat_map(|offset| [offset, -offset]);
+
+        iter::once(0).chain(offsets).find(|&offset| {
+            counter.checked_add_signed(offset).is_some_and(|candidate| {
+                hasher::ct_eq(self.hotp.generate(candidate).as_bytes(), code.as_bytes())
+            })
+        })
+    }
+
+    pub fn verify_now(&self, code: &str, drift: u64) -> Option<i64> {
+        self.verify(code, unix_time_now(), drift)
+    }
+}
+
+fn unix_time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the Unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_redacts_the_secret() {
+        let hotp = Hotp::new(b"12345678901234567890");
+        let totp = Totp::new(b"12345678901234567890").with_step(60);
+
+        assert_eq!(
+            r#"Hotp { secret: "<redacted>", algorithm: Sha1, digits: 6 }"#,
+            format!("{:?}", hotp)
+        );
+        assert_eq!(
+            r#"Totp { secret: "<redacted>", algorithm: Sha1, digits: 6, step: 60, start: 0 }"#,
+            format!("{:?}", totp)
+        );
+    }
+
+    #[test]
+    fn rfc_4226_appendix_d() {
+        let hotp = Hotp::new(b"12345678901234567890");
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+
+        for (counter, expected) in expected.iter().enumerate() {
+            assert_eq!(*expected, hotp.generate(counter as u64));
+        }
+    }
+
+    #[test]
+    fn rfc_6238_appendix_b() {
+        let seeds: [(Algorithm, &[u8]); 3] = [
+            (Algorithm::Sha1, b"12345678901234567890"),
+            (Algorithm::Sha256, b"12345678901234567890123456789012"),
+            (
+                Algorithm::Sha512,
+                b"1234567890123456789012345678901234567890123456789012345678901234",
+            ),
+        ];
+        let test_cases: &[(u64, [&str; 3])] = &[
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (1111111111, ["14050471", "67062674", "99943326"]),
+            (1234567890, ["89005924", "91819424", "93441116"]),
+            (2000000000, ["69279037", "90698825", "38618901"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+
+        for &(time, expected) in test_cases {
+            for ((algorithm, seed), expected) in seeds.iter().zip(expected) {
+                let totp = Totp::new(seed).with_algorithm(*algorithm).with_digits(8);
+                assert_eq!(
+                    expected,
+                    totp.generate(time),
+                    "Failed {} at {}",
+                    algorithm,
+                    time
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hotp_verification_resynchronizes() {
+        let hotp = Hotp::new(b"12345678901234567890");
+
+        assert_eq!(Some(0), hotp.verify("755224", 0, 0));
+        assert_eq!(Some(3), hotp.verify("969429", 1, 5));
+        assert_eq!(None, hotp.verify("969429", 4, 5));
+        assert_eq!(None, hotp.verify("969429", 0, 2));
+        assert_eq!(None, hotp.verify("96942", 3, 0));
+    }
+
+    #[test]
+    fn totp_verification_tolerates_drift() {
+        let totp = Totp::new(b"12345678901234567890").with_digits(8);
+        let time = 1111111111;
+
+        assert_eq!(Some(0), totp.verify("14050471", time, 0));
+        assert_eq!(Some(-1), totp.verify(&totp.generate(time - 30), time, 1));
+        assert_eq!(Some(2), totp.verify(&totp.generate(time + 60), time, 2));
+        assert_eq!(None, totp.verify(&totp.generate(time + 60), time, 1));
+        assert_eq!(None, totp.verify("00000000", time, 3));
+
+        assert_eq!(Some(0), totp.verify(&totp.generate(0), 0, 1));
+    }
+
+    #[test]
+    fn step_and_start_define_the_counter() {
+        let totp = Totp::new(b"secret").with_step(60).with_start(100);
+
+        assert_eq!(0, totp.counter(0));
+        assert_eq!(0, totp.counter(159));
+        assert_eq!(1, totp.counter(160));
+        assert_eq!(totp.hotp().generate(1), totp.generate(170));
+        assert_eq!(6, totp.generate_now().len());
+    }
+}
@@ -0,0 +1,313 @@
+//! `otpauth://` provisioning URIs, the Key Uri Format of Google Authenticator:
+//!
+//! ```text
+//! otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example&period=30
+//! ```
+//!
+//! The type is `hotp` or `totp`, the label names the account with an optional
+//! `issuer:` prefix. `secret` is the unpadded base32 key, `algorithm`
+//! (default `SHA1`), `digits` (default 6) and `period` (TOTP, default 30) are
+//! optional and `counter` is required for HOTP. Unknown parameters such as
+//! `image` are ignored.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Algorithm, Hotp, Totp, MAX_DIGITS};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriError {
+    InvalidScheme,
+    UnknownType(String),
+    MissingParameter(&'static str),
+    InvalidParameter(&'static str),
+    /// A malformed percent escape or an escape decoding to invalid UTF-8.
+    InvalidEncoding,
+}
+
+impl fmt::Display for UriError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UriError::InvalidScheme => write!(formatter, "URI does not start with otpauth://"),
+            UriError::UnknownType(kind) => write!(formatter, "Unknown OTP type {}", kind),
+            UriError::MissingParameter(name) => write!(formatter, "Missing parameter {}", name),
+            UriError::InvalidParameter(name) => write!(formatter, "Invalid parameter {}", name),
+            UriError::InvalidEncoding => write!(formatter, "Invalid percent encoding"),
+        }
+    }
+}
+
+impl std::error::Error for UriError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Generator {
+    /// HOTP generator with the counter to use next.
+    Hotp(Hotp, u64),
+    Totp(Totp),
+}
+
+/// `Debug` goes through the generators, which redact the secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtpAuth {
+    pub generator: Generator,
+    pub account: String,
+    pub issuer: Option<String>,
+}
+
+fn percent_decode(text: &str) -> Result<String, UriError> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte != b'%' {
+            bytes.push(byte);
+            rest = tail;
+            continue;
+        }
+
+        // `from_str_radix` alone would accept a sign such as `%+1`.
+        let hex = tail
+            .get(..2)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .ok_or(UriError::InvalidEncoding)?;
+        let hex = std::str::from_utf8(hex).map_err(|_| UriError::InvalidEncoding)?;
+        bytes.push(u8::from_str_radix(hex, 16).map_err(|_| UriError::InvalidEncoding)?);
+        rest = &tail[2..];
+    }
+
+    String::from_utf8(bytes).map_err(|_| UriError::InvalidEncoding)
+}
+
+/// Decodes RFC 4648 base32, in either case and with optional padding, as
+/// secrets are often typed by hand. Lengths no encoder produces and non-zero
+/// bits after the last byte are rejected.
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+
+    if matches!(text.len() % 8, 1 | 3 | 6) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let mut bits = 0u64;
+    let mut bit_count = 0;
+
+    for char in text.chars() {
+        let value = match char.to_ascii_uppercase() {
+            char @ 'A'..='Z' => char as u64 - 'A' as u64,
+            char @ '2'..='7' => char as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+
+        bits = bits << 5 | value;
+        bit_count += 5;
+
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+
+    Some(bytes).filter(|bytes| !bytes.is_empty() && bits == 0)
+}
+
+/// Decimal digits only, `parse` alone would accept a sign such as `+6`.
+fn parse_number<T: FromStr>(value: &str, name: &'static str) -> Result<T, UriError> {
+    if !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(UriError::InvalidParameter(name));
+    }
+
+    value.parse().map_err(|_| UriError::InvalidParameter(name))
+}
+
+impl FromStr for OtpAuth {
+    type Err = UriError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let rest = uri
+            .strip_prefix("otpauth://")
+            .ok_or(UriError::InvalidScheme)?;
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (kind, label) = path.split_once('/').unwrap_or((path, ""));
+
+        let label = percent_decode(label)?;
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.to_string()), account.trim_start()),
+            None => (None, label.as_str()),
+        };
+
+        let mut secret = None;
+        let mut issuer = None;
+        let mut algorithm = Algorithm::Sha1;
+        let mut digits = 6;
+        let mut period = 30;
+        let mut counter = None;
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let value = percent_decode(value)?;
+
+            match name {
+                "secret" => {
+                    secret =
+                        Some(decode_base32(&value).ok_or(UriError::InvalidParameter("secret"))?)
+                }
+                "issuer" => issuer = Some(value),
+                "algorithm" => algorithm = value.parse()?,
+                "digits" => digits = parse_number(&value, "digits")?,
+                "period" => period = parse_number(&value, "period")?,
+                "counter" => counter = Some(parse_number(&value, "counter")?),
+                _ => {}
+            }
+        }
+
+        let secret = secret.ok_or(UriError::MissingParameter("secret"))?;
+
+        if !(1..=MAX_DIGITS).contains(&digits) {
+            return Err(UriError::InvalidParameter("digits"));
+        }
+
+        let hotp = Hotp::new(&secret)
+            .with_algorithm(algorithm)
+            .with_digits(digits);
+
+        let generator = match kind {
+            "hotp" => Generator::Hotp(hotp, counter.ok_or(UriError::MissingParameter("counter"))?),
+            "totp" if period == 0 => return Err(UriError::InvalidParameter("period")),
+            "totp" => Generator::Totp(Totp {
+                hotp,
+                step: period,
+                start: 0,
+            }),
+            _ => return Err(UriError::UnknownType(kind.to_string())),
+        };
+
+        Ok(OtpAuth {
+            generator,
+            account: account.to_string(),
+            issuer: issuer.or(label_issuer),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_totp_uris() {
+        let uri = "otpauth://totp/ACME%20Co:john.doe@email.com?\
+                   secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&\
+                   algorithm=SHA256&digits=8&period=60&image=ignored";
+        let otp_auth: OtpAuth = uri.parse().unwrap();
+
+        assert_eq!("john.doe@email.com", otp_auth.account);
+        assert_eq!(Some("ACME Co"), otp_auth.issuer.as_deref());
+
+        let Generator::Totp(totp) = otp_auth.generator else {
+            panic!("Expected a TOTP generator");
+        };
+        assert_eq!(Algorithm::Sha256, totp.hotp().algorithm());
+        assert_eq!(8, totp.hotp().digits());
+        assert_eq!(60, totp.step());
+        assert_eq!(20, totp.hotp().secret().len());
+
+        let debug = format!("{:?}", uri.parse::<OtpAuth>().unwrap());
+        assert!(debug.contains(r#"secret: "<redacted>""#), "{}", debug);
+        assert!(!debug.contains(&format!("{:?}", totp.hotp().secret())));
+    }
+
+    #[test]
+    fn parses_hotp_uris_with_defaults() {
+        // "12345678901234567890" in base32, the RFC 4226 test secret.
+        let uri = "otpauth://hotp/alice?secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&counter=1";
+        let otp_auth: OtpAuth = uri.parse().unwrap();
+
+        assert_eq!("alice", otp_auth.account);
+        assert_eq!(None, otp_auth.issuer);
+        assert_eq!(
+            Generator::Hotp(Hotp::new(b"12345678901234567890"), 1),
+            otp_auth.generator
+        );
+
+        if let Generator::Hotp(hotp, counter) = otp_auth.generator {
+            assert_eq!("287082", hotp.generate(counter));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_uris() {
+        let test_cases: &[(&str, UriError)] = &[
+            ("https://totp/a?secret=AAAA", UriError::InvalidScheme),
+            (
+                "otpauth://motp/a?secret=AAAA",
+                UriError::UnknownType("motp".to_string()),
+            ),
+            ("otpauth://totp/a", UriError::MissingParameter("secret")),
+            (
+                "otpauth://totp/a?secret=AA1A",
+                UriError::InvalidParameter("secret"),
+            ),
+            (
+                "otpauth://totp/a?secret=AB",
+                UriError::InvalidParameter("secret"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAAAAAAA",
+                UriError::InvalidParameter("secret"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAA",
+                UriError::InvalidParameter("secret"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAAAA%3D%3D",
+                UriError::InvalidParameter("secret"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAA&digits=11",
+                UriError::InvalidParameter("digits"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAA&digits=%2B6",
+                UriError::InvalidParameter("digits"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAA&period=0",
+                UriError::InvalidParameter("period"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAA&period=+30",
+                UriError::InvalidParameter("period"),
+            ),
+            (
+                "otpauth://hotp/a?secret=AAAA&counter=+1",
+                UriError::InvalidParameter("counter"),
+            ),
+            (
+                "otpauth://totp/a?secret=AAAA&algorithm=MD5",
+                UriError::InvalidParameter("algorithm"),
+            ),
+            (
+                "otpauth://hotp/a?secret=AAAA",
+                UriError::MissingParameter("counter"),
+            ),
+            ("otpauth://totp/a%2?secret=AAAA", UriError::InvalidEncoding),
+            ("otpauth://totp/a%+1?secret=AAAA", UriError::InvalidEncoding),
+            (
+                "otpauth://totp/a?secret=AAAA&issuer=%-1",
+                UriError::InvalidEncoding,
+            ),
+        ];
+
+        for (uri, expected) in test_cases {
+            assert_eq!(
+                Err(expected.clone()),
+                uri.parse::<OtpAuth>(),
+                "Failed URI: {}",
+                uri
+            );
+        }
+    }
+}
@@ -1,7 +1,7 @@
-use clap::Parser;
-use rc5;
-use rc5::RC5WordSize;
+use clap::{Parser, ValueEnum};
+use rc5::{ChainingMode, Compatibility, Digest, FeedbackMode, RC5WordSize};
 use std::fs;
+use std::process::ExitCode;
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -23,7 +23,7 @@ struct Args {
     #[arg(short, long, default_value = "rc5_result")]
     save_path: String,
 
-    /// Number of rounds
+    /// Word size in bits: 8, 16, 32, 64 or 128
     #[arg(short, long, default_value_t = 64)]
     word_size: u8,
 
@@ -34,34 +34,116 @@ struct Args {
     /// Number of octets in key
     #[arg(short, long, default_value_t = 32)]
     bytes_key: u8,
+
+    /// Mode of operation. Without --iv, every mode but cbc-pad writes a random
+    /// IV of one block in front of the ciphertext, which is then one block
+    /// longer than the data, and reads it back from there to decrypt
+    #[arg(short, long, value_enum, default_value_t = Mode::CbcPad)]
+    mode: Mode,
+
+    /// IV in hex for every mode but cbc-pad, one block of word size / 4 bytes.
+    /// It is neither written nor read with the data, so CBC and CTS keep the
+    /// length of the data. Never reuse an IV with the same key
+    #[arg(short, long)]
+    iv: Option<Digest>,
+
+    /// Use the cipher of earlier versions, to decrypt data they produced
+    #[arg(long)]
+    legacy: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Mode {
+    /// RC5-CBC-Pad behind a random prefix
+    CbcPad,
+    /// RC5-CBC of whole blocks
+    Cbc,
+    /// RC5-CTS of at least one block
+    Cts,
+    /// CFB with 8-bit segments
+    Cfb8,
+    /// CFB with whole block segments
+    Cfb,
+    Ofb,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
 
     let word_size = match args.word_size {
+        8 => RC5WordSize::Bits8,
         16 => RC5WordSize::Bits16,
         32 => RC5WordSize::Bits32,
         64 => RC5WordSize::Bits64,
-        _ => unreachable!("Wrong word size provided: Accept only: 16, 32, 64."),
+        128 => RC5WordSize::Bits128,
+        _ => unreachable!("Wrong word size provided: Accept only: 8, 16, 32, 64, 128."),
     };
 
-    let rc5 = rc5::RC5::new(word_size, args.rounds, args.bytes_key);
+    let compatibility = if args.legacy {
+        Compatibility::Legacy
+    } else {
+        Compatibility::Standard
+    };
+
+    let rc5 =
+        rc5::RC5::new(word_size, args.rounds, args.bytes_key).with_compatibility(compatibility);
     let key = rc5.generate_key(args.key.as_bytes());
     let data = fs::read(args.file_path).expect("Unable to read data from file");
 
-    match args.operation {
-        0 => {
-            let cypher = rc5.encrypt_cbc_pad(&data, &key);
+    let encrypt = match args.operation {
+        0 => true,
+        1 => false,
+        _ => unreachable!("Wrong operation code"),
+    };
+
+    if let Some(iv) = &args.iv {
+        let block_size = args.word_size as usize / 4;
 
-            fs::write(args.save_path, cypher.0).expect("Failed to cypher text to file");
+        if let Mode::CbcPad = args.mode {
+            eprintln!("rc5-cli: cbc-pad takes no IV");
+            return ExitCode::FAILURE;
         }
-        1 => {
-            let decrypted = rc5.decrypt_cbc_pad(&data, &key);
 
-            fs::write(args.save_path, decrypted.0)
-                .expect("Failed to save decryption result to file");
+        if iv.0.len() != block_size {
+            eprintln!("rc5-cli: IV must be {} bytes long", block_size);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let chaining = |mode| match (&args.iv, encrypt) {
+        (None, true) => rc5.encrypt_chaining(mode, &data, &key),
+        (None, false) => rc5.decrypt_chaining(mode, &data, &key),
+        (Some(iv), true) => rc5.encrypt_chaining_with_iv(mode, &data, &key, &iv.0),
+        (Some(iv), false) => rc5.decrypt_chaining_with_iv(mode, &data, &key, &iv.0),
+    };
+
+    let feedback = |mode| match (&args.iv, encrypt) {
+        (None, true) => Ok(rc5.encrypt_feedback(mode, &data, &key)),
+        (None, false) => rc5.decrypt_feedback(mode, &data, &key),
+        (Some(iv), true) => Ok(rc5.encrypt_feedback_with_iv(mode, &data, &key, &iv.0)),
+        (Some(iv), false) => Ok(rc5.decrypt_feedback_with_iv(mode, &data, &key, &iv.0)),
+    };
+
+    let result = match args.mode {
+        Mode::CbcPad if encrypt => Ok(rc5.encrypt_cbc_pad(&data, &key)),
+        Mode::CbcPad => Ok(rc5.decrypt_cbc_pad(&data, &key)),
+        Mode::Cbc => chaining(ChainingMode::Cbc),
+        Mode::Cts => chaining(ChainingMode::Cts),
+        Mode::Cfb8 => feedback(FeedbackMode::Cfb8),
+        Mode::Cfb => feedback(FeedbackMode::Cfb),
+        Mode::Ofb => feedback(FeedbackMode::Ofb),
+    };
+
+    match result {
+        Ok(output) => {
+            fs::write(args.save_path, output.0).expect("Failed to save operation result to file");
+
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("rc5-cli: {}", error);
+
+            ExitCode::FAILURE
         }
-        _ => unreachable!("Wrong operation code"),
     }
 }
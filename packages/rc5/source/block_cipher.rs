@@ -0,0 +1,30 @@
+/// A keyed block cipher, the primitive the modes of operation are built on.
+///
+/// Only the in-place transformations are required, the allocating ones are
+/// provided on top of them.
+pub trait BlockCipher {
+    /// Block size in bytes.
+    fn block_size(&self) -> usize;
+
+    /// # Panics
+    ///
+    /// When `block` is not exactly one block long.
+    fn encrypt_block_in_place(&self, block: &mut [u8]);
+
+    /// # Panics
+    ///
+    /// When `block` is not exactly one block long.
+    fn decrypt_block_in_place(&self, block: &mut [u8]);
+
+    fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+        let mut output = block.to_vec();
+        self.encrypt_block_in_place(&mut output);
+        output
+    }
+
+    fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+        let mut output = block.to_vec();
+        self.decrypt_block_in_place(&mut output);
+        output
+    }
+}
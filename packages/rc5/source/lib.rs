@@ -1,28 +1,61 @@
-use md5;
-use random::LCGRandom;
-use std::time::SystemTime;
-use std::{fmt, str, vec};
+use hasher::encoding::{self, Base64};
+use std::str::FromStr;
+use std::{fmt, mem, str, vec};
 
+mod block_cipher;
+pub mod modes;
+mod word;
+
+pub use block_cipher::BlockCipher;
+pub use hasher::encoding::DecodeError;
+pub use modes::{ChainingMode, FeedbackMode, LengthError};
+pub use word::Word;
+
+#[derive(Clone, PartialEq, Eq)]
 pub struct Digest(pub Vec<u8>);
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RC5 {
     w: RC5WordSize,
     r: usize,
     b: usize,
+    compatibility: Compatibility,
+}
+
+/// Which variant of the block cipher to run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compatibility {
+    /// RC5-w/r/b as specified by Rivest and in RFC 2040.
+    #[default]
+    Standard,
+    /// The cipher of earlier versions of this crate, only to decrypt data
+    /// they produced: one round short, the last key byte ignored and the two
+    /// words of every plaintext block swapped.
+    Legacy,
 }
 
 #[derive(Clone, Debug)]
 pub enum RC5WordSize {
+    Bits8,
     Bits16,
     Bits32,
     Bits64,
+    Bits128,
 }
 
 pub enum RC5ExpandedKey {
+    Bits8(Vec<u8>),
     Bits16(Vec<u16>),
     Bits32(Vec<u32>),
     Bits64(Vec<u64>),
+    Bits128(Vec<u128>),
+}
+
+/// RC5 keyed with an expanded table `S`, computed once and reused for every
+/// block.
+pub struct Rc5Key {
+    rc5: RC5,
+    s: RC5ExpandedKey,
 }
 
 macro_rules! implement {
@@ -41,19 +74,6 @@ macro_rules! implement {
 implement!(LowerHex, "{:02x}");
 implement!(UpperHex, "{:02X}");
 
-macro_rules! rotl {
-    ($x:expr, $s:expr, $w:expr) => {
-        $x.rotate_left((($s as u32) & ($w - 1) as u32))
-            | $x.rotate_right(($w as u32) - ($s as u32 & ($w - 1) as u32))
-    };
-}
-macro_rules! rotr {
-    ($x:expr, $s:expr, $w:expr) => {
-        $x.rotate_right((($s as u32) & ($w - 1) as u32))
-            | $x.rotate_left(($w as u32) - ($s as u32 & ($w - 1) as u32))
-    };
-}
-
 impl fmt::Debug for Digest {
     #[inline]
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -67,12 +87,93 @@ impl From<Vec<u8>> for Digest {
     }
 }
 
+impl From<&[u8]> for Digest {
+    fn from(value: &[u8]) -> Self {
+        Digest(value.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Parses the hex form, in either case.
+impl FromStr for Digest {
+    type Err = DecodeError;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        if !hex.len().is_multiple_of(2) {
+            return Err(DecodeError::InvalidLength);
+        }
+
+        let mut bytes = vec![0u8; hex.len() / 2];
+        encoding::decode_hex(hex, &mut bytes)?;
+        Ok(Digest(bytes))
+    }
+}
+
+impl Digest {
+    fn encode(&self, variant: Base64) -> String {
+        let mut text = String::with_capacity(self.0.len().div_ceil(3) * 4);
+        encoding::encode_base64(&self.0, variant, &mut text)
+            .expect("Writing to a String cannot fail");
+        text
+    }
+
+    fn decode(text: &str, variant: Base64) -> Result<Self, DecodeError> {
+        let mut bytes = vec![0u8; encoding::base64_decoded_len(text)?];
+        encoding::decode_base64(text, variant, &mut bytes)?;
+        Ok(Digest(bytes))
+    }
+
+    /// Standard base64 with padding.
+    pub fn to_base64(&self) -> String {
+        self.encode(Base64::Standard)
+    }
+
+    /// URL and file name safe base64 without padding.
+    pub fn to_base64url(&self) -> String {
+        self.encode(Base64::UrlSafe)
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self, DecodeError> {
+        Digest::decode(text, Base64::Standard)
+    }
+
+    pub fn from_base64url(text: &str) -> Result<Self, DecodeError> {
+        Digest::decode(text, Base64::UrlSafe)
+    }
+
+    /// Compares in time that depends only on the lengths.
+    pub fn ct_eq(&self, other: &Digest) -> bool {
+        hasher::ct_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Digest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        encoding::serde::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Digest {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        encoding::serde::deserialize(deserializer)
+    }
+}
+
 impl From<&RC5WordSize> for usize {
     fn from(value: &RC5WordSize) -> Self {
         match value {
+            RC5WordSize::Bits8 => 8usize,
             RC5WordSize::Bits16 => 16usize,
             RC5WordSize::Bits32 => 32usize,
             RC5WordSize::Bits64 => 64usize,
+            RC5WordSize::Bits128 => 128usize,
         }
     }
 }
@@ -83,6 +184,42 @@ impl RC5 {
             w,
             r: r as usize,
             b: b as usize,
+            compatibility: Compatibility::Standard,
+        }
+    }
+
+    pub fn with_compatibility(mut self, compatibility: Compatibility) -> Self {
+        self.compatibility = compatibility;
+        self
+    }
+
+    fn is_legacy(&self) -> bool {
+        self.compatibility == Compatibility::Legacy
+    }
+
+    fn rounds(&self) -> usize {
+        if self.is_legacy() {
+            self.r.saturating_sub(1)
+        } else {
+            self.r
+        }
+    }
+
+    /// Number of key bytes loaded into `L`.
+    fn key_len(&self) -> usize {
+        if self.is_legacy() {
+            self.b.saturating_sub(1)
+        } else {
+            self.b
+        }
+    }
+
+    /// Number of steps mixing the secret key into `S`, `3 * max(t, c)`.
+    fn mixing_steps(&self, t: usize, c: usize) -> usize {
+        if self.is_legacy() {
+            3 * t
+        } else {
+            3 * t.max(c)
         }
     }
 
@@ -101,275 +238,188 @@ impl RC5 {
         }
     }
 
-    pub fn encrypt_cbc_pad(&self, pt: &[u8], k: &[u8]) -> Digest {
-        let w: usize = (&self.w).into();
-        let bb = 2 * w / 8;
-
-        let seed = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Failed to generate seed")
-            .as_nanos();
-        let mut random = LCGRandom::new(1103515245, 12345, 2147483647, seed as u32);
-
-        let iv: Vec<u8> = (0..bb)
-            .flat_map(|_| random.generate().to_le_bytes())
-            .collect();
-        let n = bb - ((iv.len() + pt.len()) % bb);
-        let padding = vec![n as u8; n];
-
+    /// Runs the key schedule of `k`, which must be `b` bytes long.
+    pub fn expand_key(&self, k: &[u8]) -> Rc5Key {
         let s = match self.w {
-            RC5WordSize::Bits16 => RC5ExpandedKey::Bits16(self.expanded_key_16(k)),
-            RC5WordSize::Bits32 => RC5ExpandedKey::Bits32(self.expanded_key_32(k)),
-            RC5WordSize::Bits64 => RC5ExpandedKey::Bits64(self.expanded_key_64(k)),
+            RC5WordSize::Bits8 => RC5ExpandedKey::Bits8(self.expanded_key(k)),
+            RC5WordSize::Bits16 => RC5ExpandedKey::Bits16(self.expanded_key(k)),
+            RC5WordSize::Bits32 => RC5ExpandedKey::Bits32(self.expanded_key(k)),
+            RC5WordSize::Bits64 => RC5ExpandedKey::Bits64(self.expanded_key(k)),
+            RC5WordSize::Bits128 => RC5ExpandedKey::Bits128(self.expanded_key(k)),
         };
 
-        [iv.as_slice(), pt, padding.as_slice()]
-            .concat()
-            .chunks(bb)
-            .scan(vec![0u8; bb], |p, b| {
-                let pt = b
-                    .iter()
-                    .zip(p.iter())
-                    .map(|(b, p)| b ^ p)
-                    .collect::<Vec<u8>>();
-                let cb = match &s {
-                    RC5ExpandedKey::Bits16(s) => self.encrypt_ecb_16(&pt, s),
-                    RC5ExpandedKey::Bits32(s) => self.encrypt_ecb_32(&pt, s),
-                    RC5ExpandedKey::Bits64(s) => self.encrypt_ecb_64(&pt, s),
-                };
-                *p = cb.clone();
-
-                Some(cb)
-            })
-            .flatten()
-            .collect::<Vec<u8>>()
-            .into()
+        Rc5Key {
+            rc5: self.clone(),
+            s,
+        }
     }
 
-    pub fn decrypt_cbc_pad(&self, ct: &[u8], k: &[u8]) -> Digest {
-        let w: usize = (&self.w).into();
-        let bb = 2 * w / 8;
-        let s = match self.w {
-            RC5WordSize::Bits16 => RC5ExpandedKey::Bits16(self.expanded_key_16(k)),
-            RC5WordSize::Bits32 => RC5ExpandedKey::Bits32(self.expanded_key_32(k)),
-            RC5WordSize::Bits64 => RC5ExpandedKey::Bits64(self.expanded_key_64(k)),
-        };
-
-        ct.chunks(bb)
-            .scan(vec![0u8; bb], |p, b| {
-                let pt = match &s {
-                    RC5ExpandedKey::Bits16(s) => self.decrypt_ecb_16(b, s),
-                    RC5ExpandedKey::Bits32(s) => self.decrypt_ecb_32(b, s),
-                    RC5ExpandedKey::Bits64(s) => self.decrypt_ecb_64(b, s),
-                };
-
-                let res = p
-                    .iter()
-                    .zip(pt.iter())
-                    .map(|(p, pt)| p ^ pt)
-                    .collect::<Vec<u8>>();
-
-                *p = b.to_vec();
-
-                Some(res)
-            })
-            .flatten()
-            .skip(w)
-            .collect::<Vec<u8>>()
-            .into()
+    /// See [`modes::encrypt_cbc_pad`], [`Rc5Key`] avoids expanding `k` again
+    /// for every message.
+    pub fn encrypt_cbc_pad(&self, pt: &[u8], k: &[u8]) -> Digest {
+        modes::encrypt_cbc_pad(&self.expand_key(k), pt)
     }
 
-    fn encrypt_ecb_16(&self, pt: &[u8], s: &[u16]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let pt = [
-            u16::from_le_bytes([pt[2], pt[3]]),
-            u16::from_le_bytes([pt[0], pt[1]]),
-        ];
-
-        let mut a = pt[0].wrapping_add(s[0]);
-        let mut b = pt[1].wrapping_add(s[1]);
-
-        for i in 1..self.r {
-            a = rotl!((a ^ b), b, w).wrapping_add(s[2 * i]);
-            b = rotl!((b ^ a), a, w).wrapping_add(s[2 * i + 1]);
-        }
-
-        [a.to_le_bytes(), b.to_le_bytes()].concat()
+    pub fn decrypt_cbc_pad(&self, ct: &[u8], k: &[u8]) -> Digest {
+        modes::decrypt_cbc_pad(&self.expand_key(k), ct)
     }
 
-    fn encrypt_ecb_32(&self, pt: &[u8], s: &[u32]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let pt = [
-            u32::from_le_bytes([pt[4], pt[5], pt[6], pt[7]]),
-            u32::from_le_bytes([pt[0], pt[1], pt[2], pt[3]]),
-        ];
-
-        let mut a = pt[0].wrapping_add(s[0]);
-        let mut b = pt[1].wrapping_add(s[1]);
-
-        for i in 1..self.r {
-            a = rotl!((a ^ b), b, w).wrapping_add(s[2 * i]);
-            b = rotl!((b ^ a), a, w).wrapping_add(s[2 * i + 1]);
-        }
+    /// See [`modes::encrypt_feedback`].
+    pub fn encrypt_feedback(&self, mode: FeedbackMode, pt: &[u8], k: &[u8]) -> Digest {
+        modes::encrypt_feedback(&self.expand_key(k), mode, pt)
+    }
 
-        [a.to_le_bytes(), b.to_le_bytes()].concat()
+    pub fn decrypt_feedback(
+        &self,
+        mode: FeedbackMode,
+        ct: &[u8],
+        k: &[u8],
+    ) -> Result<Digest, LengthError> {
+        modes::decrypt_feedback(&self.expand_key(k), mode, ct)
     }
 
-    fn encrypt_ecb_64(&self, pt: &[u8], s: &[u64]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let pt = [
-            u64::from_le_bytes(pt[8..16].try_into().unwrap()),
-            u64::from_le_bytes(pt[..8].try_into().unwrap()),
-        ];
+    /// See [`modes::encrypt_feedback_with_iv`].
+    pub fn encrypt_feedback_with_iv(
+        &self,
+        mode: FeedbackMode,
+        pt: &[u8],
+        k: &[u8],
+        iv: &[u8],
+    ) -> Digest {
+        modes::encrypt_feedback_with_iv(&self.expand_key(k), mode, iv, pt)
+    }
 
-        let mut a = pt[0].wrapping_add(s[0]);
-        let mut b = pt[1].wrapping_add(s[1]);
+    pub fn decrypt_feedback_with_iv(
+        &self,
+        mode: FeedbackMode,
+        ct: &[u8],
+        k: &[u8],
+        iv: &[u8],
+    ) -> Digest {
+        modes::decrypt_feedback_with_iv(&self.expand_key(k), mode, iv, ct)
+    }
 
-        for i in 1..self.r {
-            a = rotl!((a ^ b), b, w).wrapping_add(s[2 * i]);
-            b = rotl!((b ^ a), a, w).wrapping_add(s[2 * i + 1]);
-        }
+    /// See [`modes::encrypt_chaining`].
+    pub fn encrypt_chaining(
+        &self,
+        mode: ChainingMode,
+        pt: &[u8],
+        k: &[u8],
+    ) -> Result<Digest, LengthError> {
+        modes::encrypt_chaining(&self.expand_key(k), mode, pt)
+    }
 
-        [a.to_le_bytes(), b.to_le_bytes()].concat()
+    pub fn decrypt_chaining(
+        &self,
+        mode: ChainingMode,
+        ct: &[u8],
+        k: &[u8],
+    ) -> Result<Digest, LengthError> {
+        modes::decrypt_chaining(&self.expand_key(k), mode, ct)
     }
 
-    fn decrypt_ecb_16(&self, ct: &[u8], s: &[u16]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let ct = [
-            u16::from_le_bytes([ct[0], ct[1]]),
-            u16::from_le_bytes([ct[2], ct[3]]),
-        ];
+    /// See [`modes::encrypt_chaining_with_iv`].
+    pub fn encrypt_chaining_with_iv(
+        &self,
+        mode: ChainingMode,
+        pt: &[u8],
+        k: &[u8],
+        iv: &[u8],
+    ) -> Result<Digest, LengthError> {
+        modes::encrypt_chaining_with_iv(&self.expand_key(k), mode, iv, pt)
+    }
 
-        let mut b = ct[1];
-        let mut a = ct[0];
+    pub fn decrypt_chaining_with_iv(
+        &self,
+        mode: ChainingMode,
+        ct: &[u8],
+        k: &[u8],
+        iv: &[u8],
+    ) -> Result<Digest, LengthError> {
+        modes::decrypt_chaining_with_iv(&self.expand_key(k), mode, iv, ct)
+    }
 
-        for i in (1..self.r).rev() {
-            b = rotr!(b.wrapping_sub(s[2 * i + 1]), a, w) ^ a;
-            a = rotr!(a.wrapping_sub(s[2 * i]), b, w) ^ b;
-        }
+    /// See [`modes::Ctr`], with the keystream starting at the first counter.
+    pub fn encrypt_ctr(&self, pt: &[u8], k: &[u8], nonce: &[u8]) -> Digest {
+        let key = self.expand_key(k);
+        let mut data = pt.to_vec();
+        modes::Ctr::new(&key, nonce).apply_keystream(0, &mut data);
 
-        [
-            b.wrapping_sub(s[1]).to_le_bytes(),
-            a.wrapping_sub(s[0]).to_le_bytes(),
-        ]
-        .concat()
+        data.into()
     }
 
-    fn decrypt_ecb_32(&self, ct: &[u8], s: &[u32]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let ct = [
-            u32::from_le_bytes([ct[0], ct[1], ct[2], ct[3]]),
-            u32::from_le_bytes([ct[4], ct[5], ct[6], ct[7]]),
-        ];
+    pub fn decrypt_ctr(&self, ct: &[u8], k: &[u8], nonce: &[u8]) -> Digest {
+        self.encrypt_ctr(ct, k, nonce)
+    }
 
-        let mut b = ct[1];
-        let mut a = ct[0];
+    fn encrypt_words<W: Word>(&self, block: &mut [u8], s: &[W]) {
+        let (first, second) = block.split_at_mut(W::BYTES);
+        let mut a = W::read_le(first);
+        let mut b = W::read_le(second);
 
-        for i in (1..self.r).rev() {
-            b = rotr!(b.wrapping_sub(s[2 * i + 1]), a, w) ^ a;
-            a = rotr!(a.wrapping_sub(s[2 * i]), b, w) ^ b;
+        if self.is_legacy() {
+            mem::swap(&mut a, &mut b);
         }
 
-        [
-            b.wrapping_sub(s[1]).to_le_bytes(),
-            a.wrapping_sub(s[0]).to_le_bytes(),
-        ]
-        .concat()
-    }
-
-    fn decrypt_ecb_64(&self, ct: &[u8], s: &[u64]) -> Vec<u8> {
-        let w: usize = (&self.w).into();
-        let ct = [
-            u64::from_le_bytes(ct[..8].try_into().unwrap()),
-            u64::from_le_bytes(ct[8..16].try_into().unwrap()),
-        ];
-
-        let mut b = ct[1];
-        let mut a = ct[0];
+        a = a.wrapping_add(s[0]);
+        b = b.wrapping_add(s[1]);
 
-        for i in (1..self.r).rev() {
-            b = rotr!(b.wrapping_sub(s[2 * i + 1]), a, w) ^ a;
-            a = rotr!(a.wrapping_sub(s[2 * i]), b, w) ^ b;
+        for i in 1..=self.rounds() {
+            a = a.xor(b).rotl(b).wrapping_add(s[2 * i]);
+            b = b.xor(a).rotl(a).wrapping_add(s[2 * i + 1]);
         }
 
-        [
-            b.wrapping_sub(s[1]).to_le_bytes(),
-            a.wrapping_sub(s[0]).to_le_bytes(),
-        ]
-        .concat()
+        a.write_le(first);
+        b.write_le(second);
     }
 
-    fn expanded_key_16(&self, k: &[u8]) -> Vec<u16> {
-        let w: usize = (&self.w).into();
-        let c = (8 * self.b) / w;
-        let t = 2 * (self.r + 1);
-        let u = w / 8;
-
-        let p = 0xb7e1_u16;
-        let q = 0x9e37_u16;
-
-        let mut s = vec![0u16; t];
-        let mut l = vec![0u16; c];
+    fn decrypt_words<W: Word>(&self, block: &mut [u8], s: &[W]) {
+        let (first, second) = block.split_at_mut(W::BYTES);
+        let mut a = W::read_le(first);
+        let mut b = W::read_le(second);
 
-        for i in (0..(self.b - 1)).rev() {
-            l[i / u] = l[i / u].rotate_left(8u32).wrapping_add(k[i] as u16)
+        for i in (1..=self.rounds()).rev() {
+            b = b.wrapping_sub(s[2 * i + 1]).rotr(a).xor(a);
+            a = a.wrapping_sub(s[2 * i]).rotr(b).xor(b);
         }
 
-        s[0] = p;
-        for i in 1..t {
-            s[i] = s[i - 1].wrapping_add(q);
-        }
-
-        let mut i = 0usize;
-        let mut j = 0usize;
-        let mut a = 0u16;
-        let mut b = 0u16;
-
-        for _ in 0..(3 * t) {
-            a = rotl!((s[i].wrapping_add(a).wrapping_add(b)), 3, w);
-            s[i] = a;
-
-            b = rotl!((l[j].wrapping_add(a).wrapping_add(b)), a.wrapping_add(b), w);
-            l[j] = b;
+        b = b.wrapping_sub(s[1]);
+        a = a.wrapping_sub(s[0]);
 
-            i = (i + 1) % t;
-            j = (j + 1) % c;
+        if self.is_legacy() {
+            mem::swap(&mut a, &mut b);
         }
 
-        s
+        a.write_le(first);
+        b.write_le(second);
     }
 
-    fn expanded_key_32(&self, k: &[u8]) -> Vec<u32> {
-        let w: usize = (&self.w).into();
-        let c = (8 * self.b) / w;
+    fn expanded_key<W: Word>(&self, k: &[u8]) -> Vec<W> {
+        let u = W::BYTES;
+        let c = self.b.div_ceil(u).max(1);
         let t = 2 * (self.r + 1);
-        let u = w / 8;
 
-        let p = 0xb7e1_5163_u32;
-        let q = 0x9e37_79b9_u32;
+        let mut l = vec![W::default(); c];
 
-        let mut s = vec![0u32; t];
-        let mut l = vec![0u32; c];
-
-        for i in (0..(self.b - 1)).rev() {
-            l[i / u] = l[i / u].rotate_left(8u32).wrapping_add(k[i] as u32)
+        for i in (0..self.key_len()).rev() {
+            l[i / u] = l[i / u].rotl(W::from_u8(8)).wrapping_add(W::from_u8(k[i]));
         }
 
-        s[0] = p;
+        let mut s = vec![W::P; t];
         for i in 1..t {
-            s[i] = s[i - 1].wrapping_add(q);
+            s[i] = s[i - 1].wrapping_add(W::Q);
         }
 
         let mut i = 0usize;
         let mut j = 0usize;
-        let mut a = 0u32;
-        let mut b = 0u32;
+        let mut a = W::default();
+        let mut b = W::default();
 
-        for _ in 0..(3 * t) {
-            a = rotl!((s[i].wrapping_add(a).wrapping_add(b)), 3, w);
+        for _ in 0..self.mixing_steps(t, c) {
+            a = s[i].wrapping_add(a).wrapping_add(b).rotl(W::from_u8(3));
             s[i] = a;
 
-            b = rotl!((l[j].wrapping_add(a).wrapping_add(b)), a.wrapping_add(b), w);
+            b = l[j].wrapping_add(a).wrapping_add(b).rotl(a.wrapping_add(b));
             l[j] = b;
 
             i = (i + 1) % t;
@@ -378,45 +428,35 @@ impl RC5 {
 
         s
     }
+}
 
-    fn expanded_key_64(&self, k: &[u8]) -> Vec<u64> {
-        let w: usize = (&self.w).into();
-        let c = (8 * self.b) / w;
-        let t = 2 * (self.r + 1);
-        let u = w / 8;
-
-        let p = 0xb7e1_5162_8aed_2a6b_u64;
-        let q = 0x9e37_79b9_7f4a_7c15_u64;
+impl BlockCipher for Rc5Key {
+    fn block_size(&self) -> usize {
+        2 * usize::from(&self.rc5.w) / 8
+    }
 
-        let mut s = vec![0u64; t];
-        let mut l = vec![0u64; c];
+    fn encrypt_block_in_place(&self, block: &mut [u8]) {
+        assert_eq!(self.block_size(), block.len(), "Wrong block length");
 
-        for i in (0..(self.b - 1)).rev() {
-            l[i / u] = l[i / u].rotate_left(8u32).wrapping_add(k[i] as u64)
+        match &self.s {
+            RC5ExpandedKey::Bits8(s) => self.rc5.encrypt_words(block, s),
+            RC5ExpandedKey::Bits16(s) => self.rc5.encrypt_words(block, s),
+            RC5ExpandedKey::Bits32(s) => self.rc5.encrypt_words(block, s),
+            RC5ExpandedKey::Bits64(s) => self.rc5.encrypt_words(block, s),
+            RC5ExpandedKey::Bits128(s) => self.rc5.encrypt_words(block, s),
         }
+    }
 
-        s[0] = p;
-        for i in 1..t {
-            s[i] = s[i - 1].wrapping_add(q);
-        }
-
-        let mut i = 0usize;
-        let mut j = 0usize;
-        let mut a = 0u64;
-        let mut b = 0u64;
-
-        for _ in 0..(3 * t) {
-            a = rotl!((s[i].wrapping_add(a).wrapping_add(b)), 3, w);
-            s[i] = a;
-
-            b = rotl!((l[j].wrapping_add(a).wrapping_add(b)), a.wrapping_add(b), w);
-            l[j] = b;
+    fn decrypt_block_in_place(&self, block: &mut [u8]) {
+        assert_eq!(self.block_size(), block.len(), "Wrong block length");
 
-            i = (i + 1) % t;
-            j = (j + 1) % c;
+        match &self.s {
+            RC5ExpandedKey::Bits8(s) => self.rc5.decrypt_words(block, s),
+            RC5ExpandedKey::Bits16(s) => self.rc5.decrypt_words(block, s),
+            RC5ExpandedKey::Bits32(s) => self.rc5.decrypt_words(block, s),
+            RC5ExpandedKey::Bits64(s) => self.rc5.decrypt_words(block, s),
+            RC5ExpandedKey::Bits128(s) => self.rc5.decrypt_words(block, s),
         }
-
-        s
     }
 }
 
@@ -433,10 +473,10 @@ mod tests {
 
         let rc5 = RC5::new(RC5WordSize::Bits16, r, b);
         let key = rc5.generate_key(key_phrase);
-        let s = rc5.expanded_key_16(&key);
+        let key = rc5.expand_key(&key);
 
-        let cypher = rc5.encrypt_ecb_16(data, &s);
-        let decrypted = rc5.decrypt_ecb_16(&cypher, &s);
+        let cypher = key.encrypt_block(data);
+        let decrypted = key.decrypt_block(&cypher);
 
         assert_eq!(
             format!("{:02x}", Digest(data.to_vec())),
@@ -453,10 +493,10 @@ mod tests {
 
         let rc5 = RC5::new(RC5WordSize::Bits32, r, b);
         let key = rc5.generate_key(key_phrase);
-        let s = rc5.expanded_key_32(&key);
+        let key = rc5.expand_key(&key);
 
-        let cypher = rc5.encrypt_ecb_32(data, &s);
-        let decrypted = rc5.decrypt_ecb_32(&cypher, &s);
+        let cypher = key.encrypt_block(data);
+        let decrypted = key.decrypt_block(&cypher);
 
         assert_eq!(
             format!("{:02x}", Digest(data.to_vec())),
@@ -473,10 +513,10 @@ mod tests {
 
         let rc5 = RC5::new(RC5WordSize::Bits64, r, b);
         let key = rc5.generate_key(key_phrase);
-        let s = rc5.expanded_key_64(&key);
+        let key = rc5.expand_key(&key);
 
-        let cypher = rc5.encrypt_ecb_64(data, &s);
-        let decrypted = rc5.decrypt_ecb_64(&cypher, &s);
+        let cypher = key.encrypt_block(data);
+        let decrypted = key.decrypt_block(&cypher);
 
         assert_eq!(
             format!("{:02x}", Digest(data.to_vec())),
@@ -491,10 +531,12 @@ mod tests {
         let data = b"l";
         let key_phrase = b"HelloWorldKey";
 
-        vec![
+        [
+            RC5WordSize::Bits8,
             RC5WordSize::Bits16,
             RC5WordSize::Bits32,
             RC5WordSize::Bits64,
+            RC5WordSize::Bits128,
         ]
         .iter()
         .map(|w| {
@@ -528,4 +570,177 @@ mod tests {
             true
         });
     }
+
+    #[test]
+    fn rivest_rc5_32_12_16_vectors() {
+        let test_cases: &[(&str, &str, &str)] = &[
+            (
+                "00000000000000000000000000000000",
+                "0000000000000000",
+                "21A5DBEE154B8F6D",
+            ),
+            (
+                "915F4619BE41B2516355A50110A9CE91",
+                "21A5DBEE154B8F6D",
+                "F7C013AC5B2B8952",
+            ),
+            (
+                "783348E75AEB0F2FD7B169BB8DC16787",
+                "F7C013AC5B2B8952",
+                "2F42B3B70369FC92",
+            ),
+            (
+                "DC49DB1375A5584F6485B413B5F12BAF",
+                "2F42B3B70369FC92",
+                "65C178B284D197CC",
+            ),
+            (
+                "5269F149D41BA0152497574D7F153125",
+                "65C178B284D197CC",
+                "EB44E415DA319824",
+            ),
+        ];
+        let rc5 = RC5::new(RC5WordSize::Bits32, 12, 16);
+
+        for &(key, pt, ct) in test_cases {
+            let key = rc5.expand_key(&key.parse::<Digest>().unwrap().0);
+            let pt = pt.parse::<Digest>().unwrap().0;
+            let cypher = key.encrypt_block(&pt);
+
+            assert_eq!(ct, format!("{:02X}", Digest(cypher.clone())));
+            assert_eq!(pt, key.decrypt_block(&cypher));
+        }
+    }
+
+    #[test]
+    fn krovetz_draft_vectors() {
+        let key = |b: u8| (0..b).collect::<Vec<u8>>();
+
+        let rc5 = RC5::new(RC5WordSize::Bits8, 12, 4);
+        let cipher = rc5.expand_key(&key(4));
+        let cypher = cipher.encrypt_block(&key(2));
+        assert_eq!("212a", format!("{:02x}", Digest(cypher.clone())));
+        assert_eq!(key(2), cipher.decrypt_block(&cypher));
+
+        let rc5 = RC5::new(RC5WordSize::Bits16, 16, 8);
+        let cipher = rc5.expand_key(&key(8));
+        let cypher = cipher.encrypt_block(&key(4));
+        assert_eq!("23a8d72e", format!("{:02x}", Digest(cypher.clone())));
+        assert_eq!(key(4), cipher.decrypt_block(&cypher));
+
+        let rc5 = RC5::new(RC5WordSize::Bits32, 20, 16);
+        let cipher = rc5.expand_key(&key(16));
+        let cypher = cipher.encrypt_block(&key(8));
+        assert_eq!(
+            "2a0edc0e9431ff73",
+            format!("{:02x}", Digest(cypher.clone()))
+        );
+        assert_eq!(key(8), cipher.decrypt_block(&cypher));
+
+        let rc5 = RC5::new(RC5WordSize::Bits64, 24, 24);
+        let cipher = rc5.expand_key(&key(24));
+        let cypher = cipher.encrypt_block(&key(16));
+        assert_eq!(
+            "a46772820edbce0235abea32ae7178da",
+            format!("{:02x}", Digest(cypher.clone()))
+        );
+        assert_eq!(key(16), cipher.decrypt_block(&cypher));
+
+        let rc5 = RC5::new(RC5WordSize::Bits128, 28, 32);
+        let cipher = rc5.expand_key(&key(32));
+        let cypher = cipher.encrypt_block(&key(32));
+        assert_eq!(
+            "eca5910921a4f4cfdd7ad7ad20a1fcba068ec7a7cd752d68fe914b7fe180b440",
+            format!("{:02x}", Digest(cypher.clone()))
+        );
+        assert_eq!(key(32), cipher.decrypt_block(&cypher));
+    }
+
+    #[test]
+    fn legacy_compatibility_decrypts_old_ciphertexts() {
+        // Encrypted with the cipher before the fixes to rounds, key loading
+        // and word order.
+        let cypher: Digest = "7506e04e9361578c".parse().unwrap();
+        let key: Vec<u8> = (0..16).collect();
+
+        let rc5 = RC5::new(RC5WordSize::Bits32, 12, 16).with_compatibility(Compatibility::Legacy);
+        let cipher = rc5.expand_key(&key);
+        assert_eq!(b"abcdefgh".to_vec(), cipher.decrypt_block(&cypher.0));
+        assert_eq!(cypher.0, cipher.encrypt_block(b"abcdefgh"));
+
+        let rc5 = RC5::new(RC5WordSize::Bits32, 12, 16);
+        let cipher = rc5.expand_key(&key);
+        assert_ne!(cypher.0, cipher.encrypt_block(b"abcdefgh"));
+    }
+
+    #[test]
+    fn expanded_key_is_reused_across_messages() {
+        let rc5 = RC5::new(RC5WordSize::Bits32, 12, 16);
+        let key = rc5.expand_key(&[0x5a; 16]);
+        assert_eq!(8, key.block_size());
+
+        for len in 0..40 {
+            let pt: Vec<u8> = (0..len).collect();
+            let decrypted = modes::decrypt_cbc_pad(&key, &modes::encrypt_cbc_pad(&key, &pt).0);
+            let padding = 8 - len as usize % 8;
+
+            assert_eq!(pt.len() + padding, decrypted.0.len());
+            assert_eq!(pt, decrypted.0[..pt.len()]);
+        }
+
+        let mut block = *b"abcdefgh";
+        key.encrypt_block_in_place(&mut block);
+        assert_eq!(key.encrypt_block(b"abcdefgh"), block);
+        key.decrypt_block_in_place(&mut block);
+        assert_eq!(b"abcdefgh", &block);
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrong block length")]
+    fn blocks_must_be_whole() {
+        let key = RC5::new(RC5WordSize::Bits16, 12, 8).expand_key(&[0; 8]);
+        key.encrypt_block(b"abc");
+    }
+
+    #[test]
+    fn digest_encodings_round_trip() {
+        let digest = Digest(b"foobar".to_vec());
+
+        assert_eq!(Ok(digest.clone()), "666f6f626172".parse());
+        assert_eq!(Ok(digest.clone()), "666F6F626172".parse());
+        assert_eq!("Zm9vYmFy", digest.to_base64());
+        assert_eq!(Ok(digest.clone()), Digest::from_base64("Zm9vYmFy"));
+
+        let digest = Digest(vec![0xfb, 0xff]);
+        assert_eq!("+/8=", digest.to_base64());
+        assert_eq!("-_8", digest.to_base64url());
+        assert_eq!(Ok(digest.clone()), Digest::from_base64url("-_8"));
+        assert_eq!(&[0xfb, 0xff], digest.as_ref());
+
+        assert_eq!(Ok(Digest(Vec::new())), "".parse());
+        assert_eq!(Err(DecodeError::InvalidLength), "666".parse::<Digest>());
+        assert_eq!(
+            Err(DecodeError::InvalidPadding),
+            Digest::from_base64("+/9=")
+        );
+    }
+
+    #[test]
+    fn constant_time_digest_comparison() {
+        let digest = Digest(b"abcdefgh".to_vec());
+
+        assert!(digest.ct_eq(&Digest(b"abcdefgh".to_vec())));
+        assert!(!digest.ct_eq(&Digest(b"abcdefgi".to_vec())));
+        assert!(!digest.ct_eq(&Digest(b"abcdefg".to_vec())));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_uses_hex_in_json() {
+        let digest = Digest(vec![0x00, 0xab, 0xff]);
+        let json = serde_json::to_string(&digest).unwrap();
+
+        assert_eq!("\"00abff\"", json);
+        assert_eq!(digest, serde_json::from_str::<Digest>(&json).unwrap());
+    }
 }
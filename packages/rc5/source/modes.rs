@@ -0,0 +1,972 @@
+//! Modes of operation over any [`BlockCipher`].
+
+use std::fs::File;
+use std::io::Read;
+use std::{fmt, mem};
+
+use crate::{BlockCipher, Digest};
+
+/// Length of the random prefix, in blocks, that [`encrypt_cbc_pad`] encrypts
+/// ahead of the plaintext in place of a transmitted IV.
+const RANDOM_PREFIX_BLOCKS: usize = 4;
+
+/// Input whose length the mode cannot process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthError {
+    /// The ciphertext is shorter than the IV in front of it.
+    MissingIv,
+    /// CBC without padding takes whole blocks only.
+    PartialBlock,
+    /// CTS needs at least one block to steal from.
+    ShorterThanBlock,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthError::MissingIv => write!(formatter, "Ciphertext is shorter than the IV"),
+            LengthError::PartialBlock => write!(formatter, "Data is not a whole number of blocks"),
+            LengthError::ShorterThanBlock => write!(formatter, "Data is shorter than one block"),
+        }
+    }
+}
+
+impl std::error::Error for LengthError {}
+
+/// IVs and prefixes from the random generator of the operating system.
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+
+    File::open("/dev/urandom")
+        .and_then(|mut urandom| urandom.read_exact(&mut bytes))
+        .expect("Failed to read /dev/urandom");
+
+    bytes
+}
+
+/// CBC with a zero IV over a random prefix, the plaintext and PKCS #7 style
+/// padding of 1 to block size bytes.
+pub fn encrypt_cbc_pad<C: BlockCipher + ?Sized>(cipher: &C, pt: &[u8]) -> Digest {
+    let bb = cipher.block_size();
+    let iv = random_bytes(RANDOM_PREFIX_BLOCKS * bb);
+    let n = bb - ((iv.len() + pt.len()) % bb);
+    let padding = vec![n as u8; n];
+
+    [iv.as_slice(), pt, padding.as_slice()]
+        .concat()
+        .chunks(bb)
+        .scan(vec![0u8; bb], |p, b| {
+            for (p, b) in p.iter_mut().zip(b) {
+                *p ^= b;
+            }
+            cipher.encrypt_block_in_place(p);
+
+            Some(p.clone())
+        })
+        .flatten()
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+/// Inverse of [`encrypt_cbc_pad`]. The random prefix is dropped, the padding
+/// is left for the caller to check and strip.
+pub fn decrypt_cbc_pad<C: BlockCipher + ?Sized>(cipher: &C, ct: &[u8]) -> Digest {
+    let bb = cipher.block_size();
+
+    ct.chunks(bb)
+        .scan(vec![0u8; bb], |p, b| {
+            let pt = cipher.decrypt_block(b);
+
+            let res = p
+                .iter()
+                .zip(pt.iter())
+                .map(|(p, pt)| p ^ pt)
+                .collect::<Vec<u8>>();
+
+            *p = b.to_vec();
+
+            Some(res)
+        })
+        .flatten()
+        .skip(RANDOM_PREFIX_BLOCKS * bb)
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+/// Modes turning the block cipher into a self-synchronizing or synchronous
+/// stream cipher, as in NIST SP 800-38A. None of them pads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedbackMode {
+    /// CFB with 8-bit segments: one block encryption per byte.
+    Cfb8,
+    /// CFB with segments of a whole block.
+    Cfb,
+    Ofb,
+}
+
+/// Encrypts `pt` under a random IV of one block, which is prepended to the
+/// ciphertext: the output is one block longer than `pt`.
+pub fn encrypt_feedback<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: FeedbackMode,
+    pt: &[u8],
+) -> Digest {
+    let iv = random_bytes(cipher.block_size());
+    let ct = encrypt_feedback_with_iv(cipher, mode, &iv, pt);
+
+    [iv, ct.0].concat().into()
+}
+
+/// Inverse of [`encrypt_feedback`].
+pub fn decrypt_feedback<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: FeedbackMode,
+    ct: &[u8],
+) -> Result<Digest, LengthError> {
+    let (iv, ct) = split_iv(cipher, ct)?;
+    Ok(decrypt_feedback_with_iv(cipher, mode, iv, &ct))
+}
+
+/// Encrypts `pt` under an IV chosen by the caller, the ciphertext has the
+/// length of `pt`. An IV must never be reused with the same key: OFB then
+/// repeats the keystream and CFB the first one.
+///
+/// # Panics
+///
+/// When `iv` is not one block long.
+pub fn encrypt_feedback_with_iv<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: FeedbackMode,
+    iv: &[u8],
+    pt: &[u8],
+) -> Digest {
+    let mut data = pt.to_vec();
+
+    match mode {
+        FeedbackMode::Cfb8 => encrypt_cfb8(cipher, iv, &mut data),
+        FeedbackMode::Cfb => encrypt_cfb(cipher, iv, &mut data),
+        FeedbackMode::Ofb => apply_ofb(cipher, iv, &mut data),
+    }
+
+    data.into()
+}
+
+/// Inverse of [`encrypt_feedback_with_iv`].
+pub fn decrypt_feedback_with_iv<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: FeedbackMode,
+    iv: &[u8],
+    ct: &[u8],
+) -> Digest {
+    let mut data = ct.to_vec();
+
+    match mode {
+        FeedbackMode::Cfb8 => decrypt_cfb8(cipher, iv, &mut data),
+        FeedbackMode::Cfb => decrypt_cfb(cipher, iv, &mut data),
+        FeedbackMode::Ofb => apply_ofb(cipher, iv, &mut data),
+    }
+
+    data.into()
+}
+
+/// The modes of RFC 2040 besides RC5-CBC-Pad, which leave the length of the
+/// data unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainingMode {
+    /// CBC of data made of whole blocks.
+    Cbc,
+    /// CBC with ciphertext stealing, for any data of at least one block.
+    Cts,
+}
+
+/// Encrypts `pt` under a random IV of one block, which is prepended to the
+/// ciphertext: the output is one block longer than `pt`. RFC 2040 transmits
+/// the IV apart from the ciphertext, as [`encrypt_chaining_with_iv`] does.
+pub fn encrypt_chaining<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: ChainingMode,
+    pt: &[u8],
+) -> Result<Digest, LengthError> {
+    let iv = random_bytes(cipher.block_size());
+    let ct = encrypt_chaining_with_iv(cipher, mode, &iv, pt)?;
+
+    Ok([iv, ct.0].concat().into())
+}
+
+/// Inverse of [`encrypt_chaining`].
+pub fn decrypt_chaining<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: ChainingMode,
+    ct: &[u8],
+) -> Result<Digest, LengthError> {
+    let (iv, ct) = split_iv(cipher, ct)?;
+    decrypt_chaining_with_iv(cipher, mode, iv, &ct)
+}
+
+/// Encrypts `pt` under an IV chosen by the caller, the ciphertext has the
+/// length of `pt`. The IV has to be unpredictable, an attacker who knows it
+/// ahead of time can choose plaintexts that test guesses about earlier
+/// blocks.
+///
+/// # Panics
+///
+/// When `iv` is not one block long.
+pub fn encrypt_chaining_with_iv<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: ChainingMode,
+    iv: &[u8],
+    pt: &[u8],
+) -> Result<Digest, LengthError> {
+    let mut data = pt.to_vec();
+
+    match mode {
+        ChainingMode::Cbc => encrypt_cbc(cipher, iv, &mut data)?,
+        ChainingMode::Cts => encrypt_cts(cipher, iv, &mut data)?,
+    }
+
+    Ok(data.into())
+}
+
+/// Inverse of [`encrypt_chaining_with_iv`].
+pub fn decrypt_chaining_with_iv<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mode: ChainingMode,
+    iv: &[u8],
+    ct: &[u8],
+) -> Result<Digest, LengthError> {
+    let mut data = ct.to_vec();
+
+    match mode {
+        ChainingMode::Cbc => decrypt_cbc(cipher, iv, &mut data)?,
+        ChainingMode::Cts => decrypt_cts(cipher, iv, &mut data)?,
+    }
+
+    Ok(data.into())
+}
+
+/// Separates the leading IV from a copy of the ciphertext after it.
+fn split_iv<'a, C: BlockCipher + ?Sized>(
+    cipher: &C,
+    ct: &'a [u8],
+) -> Result<(&'a [u8], Vec<u8>), LengthError> {
+    if ct.len() < cipher.block_size() {
+        return Err(LengthError::MissingIv);
+    }
+
+    let (iv, ct) = ct.split_at(cipher.block_size());
+    Ok((iv, ct.to_vec()))
+}
+
+fn check_iv<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8]) {
+    assert_eq!(cipher.block_size(), iv.len(), "IV must be one block long");
+}
+
+/// CBC in place, RC5-CBC of RFC 2040: every plaintext block is XORed with
+/// the previous ciphertext block, the IV for the first one, and encrypted.
+///
+/// # Panics
+///
+/// When `iv` is not one block long, as for CTS.
+pub fn encrypt_cbc<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    data: &mut [u8],
+) -> Result<(), LengthError> {
+    check_iv(cipher, iv);
+
+    if !data.len().is_multiple_of(cipher.block_size()) {
+        return Err(LengthError::PartialBlock);
+    }
+
+    let mut previous: &[u8] = iv;
+
+    for block in data.chunks_mut(cipher.block_size()) {
+        for (byte, previous) in block.iter_mut().zip(previous) {
+            *byte ^= previous;
+        }
+
+        cipher.encrypt_block_in_place(block);
+        previous = block;
+    }
+
+    Ok(())
+}
+
+pub fn decrypt_cbc<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    data: &mut [u8],
+) -> Result<(), LengthError> {
+    check_iv(cipher, iv);
+
+    if !data.len().is_multiple_of(cipher.block_size()) {
+        return Err(LengthError::PartialBlock);
+    }
+
+    let mut previous = iv.to_vec();
+
+    for block in data.chunks_mut(cipher.block_size()) {
+        let ct = block.to_vec();
+        cipher.decrypt_block_in_place(block);
+
+        for (byte, previous) in block.iter_mut().zip(&previous) {
+            *byte ^= previous;
+        }
+
+        previous = ct;
+    }
+
+    Ok(())
+}
+
+/// Length of the data before the last, possibly partial, block and the
+/// length of that block.
+fn split_last_block(len: usize, bb: usize) -> (usize, usize) {
+    let last = (len - 1) % bb + 1;
+    (len - last, last)
+}
+
+/// RC5-CTS of RFC 2040 in place. All but the last block go through CBC,
+/// then the last block `P_n` of `m` bytes is padded with zeros, XORed with
+/// the CBC output `E_(n-1)` of the block before it and encrypted into
+/// `C_(n-1)`. The first `m` bytes of `E_(n-1)` become `C_n`, so the last two
+/// blocks are swapped even when the data is whole blocks. A single block is
+/// plain CBC.
+pub fn encrypt_cts<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    data: &mut [u8],
+) -> Result<(), LengthError> {
+    let bb = cipher.block_size();
+
+    if data.len() < bb {
+        check_iv(cipher, iv);
+        return Err(LengthError::ShorterThanBlock);
+    }
+
+    let (head, m) = split_last_block(data.len(), bb);
+
+    if head == 0 {
+        return encrypt_cbc(cipher, iv, data);
+    }
+
+    encrypt_cbc(cipher, iv, &mut data[..head])?;
+
+    let (body, last) = data.split_at_mut(head);
+    let stolen = body[head - bb..].to_vec();
+
+    let previous = &mut body[head - bb..];
+    for (byte, last) in previous.iter_mut().zip(last.iter()) {
+        *byte ^= last;
+    }
+    cipher.encrypt_block_in_place(previous);
+
+    last.copy_from_slice(&stolen[..m]);
+
+    Ok(())
+}
+
+pub fn decrypt_cts<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    data: &mut [u8],
+) -> Result<(), LengthError> {
+    let bb = cipher.block_size();
+
+    if data.len() < bb {
+        check_iv(cipher, iv);
+        return Err(LengthError::ShorterThanBlock);
+    }
+
+    let (head, _) = split_last_block(data.len(), bb);
+
+    if head == 0 {
+        return decrypt_cbc(cipher, iv, data);
+    }
+
+    let (body, last) = data.split_at_mut(head);
+
+    // D_n = P_n | 0 ^ E_(n-1): its head XORed with C_n, the head of
+    // E_(n-1), gives P_n and its tail is the tail of E_(n-1).
+    let previous = &mut body[head - bb..];
+    cipher.decrypt_block_in_place(previous);
+
+    for (byte, last) in previous.iter_mut().zip(last.iter_mut()) {
+        *byte ^= *last;
+        mem::swap(byte, last);
+    }
+
+    decrypt_cbc(cipher, iv, body)
+}
+
+/// CFB-8 in place: every byte is XORed with the first byte of the encrypted
+/// shift register, which then shifts the ciphertext byte in.
+///
+/// # Panics
+///
+/// When `iv` is not one block long, as for the other feedback modes.
+pub fn encrypt_cfb8<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8]) {
+    cfb8(cipher, iv, data, true)
+}
+
+pub fn decrypt_cfb8<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8]) {
+    cfb8(cipher, iv, data, false)
+}
+
+fn cfb8<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8], encrypt: bool) {
+    check_iv(cipher, iv);
+    let mut register = iv.to_vec();
+
+    for byte in data {
+        let input = *byte;
+        *byte ^= cipher.encrypt_block(&register)[0];
+
+        register.rotate_left(1);
+        *register.last_mut().unwrap() = if encrypt { *byte } else { input };
+    }
+}
+
+/// Full-block CFB in place: every block is XORed with the encryption of the
+/// previous ciphertext block, the IV for the first one. A final partial
+/// block uses the leading bytes of its keystream.
+pub fn encrypt_cfb<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8]) {
+    cfb(cipher, iv, data, true)
+}
+
+pub fn decrypt_cfb<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8]) {
+    cfb(cipher, iv, data, false)
+}
+
+fn cfb<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8], encrypt: bool) {
+    check_iv(cipher, iv);
+    let mut register = iv.to_vec();
+
+    for chunk in data.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block_in_place(&mut register);
+
+        for (byte, key) in chunk.iter_mut().zip(register.iter_mut()) {
+            let input = *byte;
+            *byte ^= *key;
+            *key = if encrypt { *byte } else { input };
+        }
+    }
+}
+
+/// OFB in place, encrypting and decrypting alike: the keystream is the IV
+/// encrypted over and over, independent of the data.
+pub fn apply_ofb<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8], data: &mut [u8]) {
+    check_iv(cipher, iv);
+    let mut register = iv.to_vec();
+
+    for chunk in data.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block_in_place(&mut register);
+
+        for (byte, key) in chunk.iter_mut().zip(&register) {
+            *byte ^= key;
+        }
+    }
+}
+
+/// Counter mode: the keystream is the encryption of successive counter
+/// blocks, made of the nonce followed by the big-endian block number in the
+/// remaining bytes. The nonce length therefore chooses how many blocks a
+/// single nonce may cover, and it must never be reused with the same key.
+pub struct Ctr<'a, C: BlockCipher + ?Sized> {
+    cipher: &'a C,
+    nonce: Vec<u8>,
+}
+
+impl<'a, C: BlockCipher + ?Sized> Ctr<'a, C> {
+    /// # Panics
+    ///
+    /// When `nonce` leaves no byte of the block for the counter.
+    pub fn new(cipher: &'a C, nonce: &[u8]) -> Self {
+        assert!(
+            nonce.len() < cipher.block_size(),
+            "Nonce leaves no room for the counter"
+        );
+
+        Ctr {
+            cipher,
+            nonce: nonce.to_vec(),
+        }
+    }
+
+    fn keystream_block(&self, index: u64) -> Vec<u8> {
+        let bb = self.cipher.block_size();
+        let counter_len = (bb - self.nonce.len()).min(8);
+
+        assert!(
+            counter_len == 8 || index >> (8 * counter_len) == 0,
+            "Counter overflow, the keystream would repeat"
+        );
+
+        let mut block = self.nonce.clone();
+        block.resize(bb, 0);
+        block[bb - counter_len..].copy_from_slice(&index.to_be_bytes()[8 - counter_len..]);
+        self.cipher.encrypt_block_in_place(&mut block);
+
+        block
+    }
+
+    /// XORs the keystream into `data` as if it started at byte `offset` of
+    /// the message, which both encrypts and decrypts any part of it.
+    ///
+    /// # Panics
+    ///
+    /// When the message runs past the last counter value.
+    pub fn apply_keystream(&self, offset: u64, data: &mut [u8]) {
+        let bb = self.cipher.block_size();
+        let mut index = offset / bb as u64;
+        let mut skip = (offset % bb as u64) as usize;
+        let mut rest = data;
+
+        while !rest.is_empty() {
+            let keystream = self.keystream_block(index);
+            let (chunk, tail) = rest.split_at_mut((bb - skip).min(rest.len()));
+
+            for (byte, key) in chunk.iter_mut().zip(&keystream[skip..]) {
+                *byte ^= key;
+            }
+
+            rest = tail;
+            skip = 0;
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RC5WordSize, Rc5Key, RC5};
+
+    fn keys() -> Vec<Rc5Key> {
+        [
+            RC5WordSize::Bits8,
+            RC5WordSize::Bits16,
+            RC5WordSize::Bits32,
+            RC5WordSize::Bits64,
+            RC5WordSize::Bits128,
+        ]
+        .into_iter()
+        .map(|w| RC5::new(w, 12, 16).expand_key(b"0123456789abcdef"))
+        .collect()
+    }
+
+    fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+        a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+    }
+
+    #[test]
+    fn cfb8_follows_the_shift_register() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv: Vec<u8> = (0..bb as u8).collect();
+            let pt: Vec<u8> = (0..2 * bb as u8 + 1).map(|i| i.wrapping_mul(29)).collect();
+
+            let mut ct = pt.clone();
+            encrypt_cfb8(&key, &iv, &mut ct);
+
+            // I_1 = IV, I_j = LSB(I_(j-1)) | C_(j-1), C_j = P_j ^ MSB(E(I_j))
+            let mut input = iv.clone();
+            for (p, c) in pt.iter().zip(&ct) {
+                assert_eq!(p ^ key.encrypt_block(&input)[0], *c, "Block size {}", bb);
+                input = [&input[1..], &[*c]].concat();
+            }
+
+            decrypt_cfb8(&key, &iv, &mut ct);
+            assert_eq!(pt, ct);
+        }
+    }
+
+    #[test]
+    fn cfb_chains_ciphertext_blocks() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0x5c; bb];
+            let pt: Vec<u8> = (0..3 * bb as u8 - 1).collect();
+
+            let mut ct = pt.clone();
+            encrypt_cfb(&key, &iv, &mut ct);
+
+            // C_1 = P_1 ^ E(IV), C_j = P_j ^ E(C_(j-1)), the last one truncated
+            let mut input = iv.clone();
+            for (p, c) in pt.chunks(bb).zip(ct.chunks(bb)) {
+                assert_eq!(xor(p, &key.encrypt_block(&input)), c, "Block size {}", bb);
+                input = c.to_vec();
+            }
+
+            decrypt_cfb(&key, &iv, &mut ct);
+            assert_eq!(pt, ct);
+        }
+    }
+
+    #[test]
+    fn ofb_keystream_ignores_the_data() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0xc3; bb];
+
+            // O_1 = E(IV), O_j = E(O_(j-1))
+            let mut keystream = vec![0u8; 3 * bb];
+            apply_ofb(&key, &iv, &mut keystream);
+
+            let first = key.encrypt_block(&iv);
+            let second = key.encrypt_block(&first);
+            let third = key.encrypt_block(&second);
+            assert_eq!([first, second, third].concat(), keystream);
+
+            let pt: Vec<u8> = (0..3 * bb as u8 - 1).collect();
+            let mut ct = pt.clone();
+            apply_ofb(&key, &iv, &mut ct);
+            assert_eq!(xor(&pt, &keystream), ct);
+
+            apply_ofb(&key, &iv, &mut ct);
+            assert_eq!(pt, ct);
+        }
+    }
+
+    #[test]
+    fn bit_errors_propagate_as_specified() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0x17; bb];
+            let pt: Vec<u8> = (0..4 * bb as u8).collect();
+            let damaged = |mode: FeedbackMode| {
+                let mut ct = pt.clone();
+                match mode {
+                    FeedbackMode::Cfb8 => encrypt_cfb8(&key, &iv, &mut ct),
+                    FeedbackMode::Cfb => encrypt_cfb(&key, &iv, &mut ct),
+                    FeedbackMode::Ofb => apply_ofb(&key, &iv, &mut ct),
+                }
+
+                ct[bb] ^= 0x01;
+
+                match mode {
+                    FeedbackMode::Cfb8 => decrypt_cfb8(&key, &iv, &mut ct),
+                    FeedbackMode::Cfb => decrypt_cfb(&key, &iv, &mut ct),
+                    FeedbackMode::Ofb => apply_ofb(&key, &iv, &mut ct),
+                }
+
+                (0..ct.len())
+                    .filter(|&i| ct[i] != pt[i])
+                    .collect::<Vec<_>>()
+            };
+
+            // The flipped bit itself, then whatever the damaged ciphertext
+            // feeds back into: one block for CFB, the next bb bytes for CFB-8.
+            let ofb = damaged(FeedbackMode::Ofb);
+            assert_eq!(vec![bb], ofb);
+
+            let cfb = damaged(FeedbackMode::Cfb);
+            assert_eq!(bb, cfb[0]);
+            assert!(cfb
+                .iter()
+                .all(|&i| i == bb || (2 * bb..3 * bb).contains(&i)));
+
+            let cfb8 = damaged(FeedbackMode::Cfb8);
+            assert_eq!(bb, cfb8[0]);
+            assert!(cfb8.iter().all(|&i| (bb..=2 * bb).contains(&i)));
+        }
+    }
+
+    #[test]
+    fn feedback_modes_prepend_the_iv() {
+        for key in keys() {
+            for mode in [FeedbackMode::Cfb8, FeedbackMode::Cfb, FeedbackMode::Ofb] {
+                let pt = b"seventeen bytes!!";
+                let ct = encrypt_feedback(&key, mode, pt);
+
+                assert_eq!(key.block_size() + pt.len(), ct.0.len());
+                assert_eq!(pt.to_vec(), decrypt_feedback(&key, mode, &ct.0).unwrap().0);
+
+                let (iv, body) = ct.0.split_at(key.block_size());
+                assert_eq!(body, encrypt_feedback_with_iv(&key, mode, iv, pt).0);
+                assert_eq!(
+                    pt.to_vec(),
+                    decrypt_feedback_with_iv(&key, mode, iv, body).0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn random_ivs_differ() {
+        let key = &keys()[2];
+        let pt = [0u8; 8];
+        let first = encrypt_feedback(key, FeedbackMode::Ofb, &pt);
+        let second = encrypt_feedback(key, FeedbackMode::Ofb, &pt);
+
+        assert_ne!(first.0[..8], second.0[..8]);
+    }
+
+    #[test]
+    fn rfc_2040_rc5_cbc_vectors() {
+        let test_cases: &[(u8, &str, &str, &str, &str)] = &[
+            (
+                0,
+                "00",
+                "0000000000000000",
+                "0000000000000000",
+                "7a7bba4d79111d1e",
+            ),
+            (
+                0,
+                "00",
+                "0102030405060708",
+                "1020304050607080",
+                "8b9ded91ce7794a6",
+            ),
+            (
+                1,
+                "11",
+                "0000000000000000",
+                "0000000000000000",
+                "2f759fe7ad86a378",
+            ),
+            (
+                8,
+                "00",
+                "0102030405060708",
+                "1020304050607080",
+                "9646fb77638f9ca8",
+            ),
+            (
+                8,
+                "0102030405060708",
+                "0102030405060708",
+                "1020304050607080",
+                "5c4c041e0f217ac3",
+            ),
+        ];
+
+        for &(r, key, iv, pt, ct) in test_cases {
+            let key: Digest = key.parse().unwrap();
+            let key = RC5::new(RC5WordSize::Bits32, r, key.0.len() as u8).expand_key(&key.0);
+            let iv: Digest = iv.parse().unwrap();
+            let mut data: Digest = pt.parse().unwrap();
+
+            encrypt_cbc(&key, &iv.0, &mut data.0).unwrap();
+            assert_eq!(ct, format!("{:02x}", data));
+
+            decrypt_cbc(&key, &iv.0, &mut data.0).unwrap();
+            assert_eq!(pt, format!("{:02x}", data));
+        }
+    }
+
+    #[test]
+    fn cbc_takes_whole_blocks_only() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0x69; bb];
+            let pt: Vec<u8> = (0..3 * bb as u8).collect();
+
+            let mut ct = pt.clone();
+            encrypt_cbc(&key, &iv, &mut ct).unwrap();
+            assert_eq!(key.encrypt_block(&xor(&pt[..bb], &iv)), ct[..bb]);
+            assert_eq!(
+                key.encrypt_block(&xor(&pt[bb..2 * bb], &ct[..bb])),
+                ct[bb..2 * bb]
+            );
+
+            decrypt_cbc(&key, &iv, &mut ct).unwrap();
+            assert_eq!(pt, ct);
+
+            let mut partial = vec![0u8; bb + 1];
+            assert_eq!(
+                Err(LengthError::PartialBlock),
+                encrypt_cbc(&key, &iv, &mut partial)
+            );
+            assert_eq!(
+                Err(LengthError::PartialBlock),
+                decrypt_cbc(&key, &iv, &mut partial)
+            );
+        }
+    }
+
+    #[test]
+    fn cts_keeps_the_length_of_the_data() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0x96; bb];
+
+            for len in bb..4 * bb + 2 {
+                let pt: Vec<u8> = (0..len as u8).map(|i| i.wrapping_mul(13)).collect();
+
+                let mut ct = pt.clone();
+                encrypt_cts(&key, &iv, &mut ct).unwrap();
+                assert_eq!(pt.len(), ct.len());
+
+                decrypt_cts(&key, &iv, &mut ct).unwrap();
+                assert_eq!(pt, ct, "Block size {}, length {}", bb, len);
+            }
+
+            let mut short = vec![0u8; bb - 1];
+            assert_eq!(
+                Err(LengthError::ShorterThanBlock),
+                encrypt_cts(&key, &iv, &mut short)
+            );
+            assert_eq!(
+                Err(LengthError::ShorterThanBlock),
+                decrypt_cts(&key, &iv, &mut short)
+            );
+        }
+    }
+
+    #[test]
+    fn cts_steals_from_the_cbc_ciphertext() {
+        for key in keys() {
+            let bb = key.block_size();
+            let iv = vec![0x0f; bb];
+            let pt: Vec<u8> = (0..3 * bb as u8).collect();
+
+            let mut cbc = pt.clone();
+            encrypt_cbc(&key, &iv, &mut cbc).unwrap();
+
+            // Single block: plain CBC
+            let mut cts = pt[..bb].to_vec();
+            encrypt_cts(&key, &iv, &mut cts).unwrap();
+            assert_eq!(cbc[..bb], cts);
+
+            // Whole blocks: CBC with the last two blocks swapped
+            let mut cts = pt.clone();
+            encrypt_cts(&key, &iv, &mut cts).unwrap();
+            assert_eq!(cbc[..bb], cts[..bb]);
+            assert_eq!(cbc[2 * bb..], cts[bb..2 * bb]);
+            assert_eq!(cbc[bb..2 * bb], cts[2 * bb..]);
+
+            // Partial last block: C_n is the head of E_(n-1) and C_(n-1) the
+            // encryption of the zero padded P_n XORed with E_(n-1)
+            let m = bb / 2 + 1;
+            let mut cts = pt[..2 * bb + m].to_vec();
+            encrypt_cts(&key, &iv, &mut cts).unwrap();
+
+            let mut last = pt[2 * bb..2 * bb + m].to_vec();
+            last.resize(bb, 0);
+            assert_eq!(cbc[..bb], cts[..bb]);
+            assert_eq!(
+                key.encrypt_block(&xor(&last, &cbc[bb..2 * bb])),
+                cts[bb..2 * bb]
+            );
+            assert_eq!(cbc[bb..bb + m], cts[2 * bb..]);
+        }
+    }
+
+    #[test]
+    fn chaining_modes_prepend_the_iv() {
+        for key in keys() {
+            let bb = key.block_size();
+            let pt = vec![0xaa; 2 * bb];
+
+            for mode in [ChainingMode::Cbc, ChainingMode::Cts] {
+                let ct = encrypt_chaining(&key, mode, &pt).unwrap();
+
+                assert_eq!(bb + pt.len(), ct.0.len());
+                assert_eq!(pt, decrypt_chaining(&key, mode, &ct.0).unwrap().0);
+                assert_eq!(
+                    Err(LengthError::MissingIv),
+                    decrypt_chaining(&key, mode, &ct.0[..bb - 1])
+                );
+            }
+
+            assert_eq!(
+                Err(LengthError::PartialBlock),
+                encrypt_chaining(&key, ChainingMode::Cbc, &pt[1..])
+            );
+            assert!(encrypt_chaining(&key, ChainingMode::Cts, &pt[1..]).is_ok());
+        }
+    }
+
+    #[test]
+    fn chaining_modes_with_an_iv_keep_the_length() {
+        for key in keys() {
+            let bb = key.block_size();
+            let pt: Vec<u8> = (0..2 * bb as u8 + 3).collect();
+            let ct = encrypt_chaining(&key, ChainingMode::Cts, &pt).unwrap();
+            let (iv, body) = ct.0.split_at(bb);
+
+            let cts = encrypt_chaining_with_iv(&key, ChainingMode::Cts, iv, &pt).unwrap();
+            assert_eq!(body, cts.0);
+            assert_eq!(
+                pt,
+                decrypt_chaining_with_iv(&key, ChainingMode::Cts, iv, &cts.0)
+                    .unwrap()
+                    .0
+            );
+
+            let cbc = encrypt_chaining_with_iv(&key, ChainingMode::Cbc, iv, &pt[..bb]).unwrap();
+            assert_eq!(bb, cbc.0.len());
+            assert_eq!(
+                Err(LengthError::ShorterThanBlock),
+                encrypt_chaining_with_iv(&key, ChainingMode::Cts, iv, &pt[..bb - 1])
+            );
+        }
+    }
+
+    #[test]
+    fn ctr_round_trips_without_padding() {
+        for key in keys() {
+            let bb = key.block_size();
+            let ctr = Ctr::new(&key, &vec![0xa5; bb / 2]);
+
+            for len in 0..3 * bb + 2 {
+                let pt: Vec<u8> = (0..len as u8).collect();
+                let mut data = pt.clone();
+
+                ctr.apply_keystream(0, &mut data);
+                assert_eq!(pt.len(), data.len());
+                assert!(len == 0 || data != pt, "Block size {}", bb);
+
+                ctr.apply_keystream(0, &mut data);
+                assert_eq!(pt, data, "Block size {}", bb);
+            }
+        }
+    }
+
+    #[test]
+    fn ctr_seeks_to_any_offset() {
+        for key in keys() {
+            let bb = key.block_size();
+            let ctr = Ctr::new(&key, &vec![0x3c; bb / 2]);
+            let pt: Vec<u8> = (0..4 * bb as u8 + 3).collect();
+
+            let mut ct = pt.clone();
+            ctr.apply_keystream(0, &mut ct);
+
+            for offset in 0..pt.len() {
+                for end in offset..=pt.len() {
+                    let mut part = ct[offset..end].to_vec();
+                    ctr.apply_keystream(offset as u64, &mut part);
+
+                    assert_eq!(pt[offset..end], part, "Block size {}", bb);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ctr_counter_blocks_follow_the_nonce() {
+        for key in keys() {
+            let bb = key.block_size();
+            let nonce = vec![0x42; bb - 1];
+            let ctr = Ctr::new(&key, &nonce);
+
+            let mut keystream = vec![0u8; 2 * bb];
+            ctr.apply_keystream(255 * bb as u64, &mut keystream[..bb]);
+            ctr.apply_keystream(bb as u64, &mut keystream[bb..]);
+
+            let block = |counter: u8| key.encrypt_block(&[nonce.as_slice(), &[counter]].concat());
+            assert_eq!([block(255), block(1)].concat(), keystream);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Counter overflow")]
+    fn ctr_refuses_to_repeat_the_keystream() {
+        let key = RC5::new(RC5WordSize::Bits16, 12, 16).expand_key(&[0; 16]);
+        let ctr = Ctr::new(&key, &[0, 0, 0]);
+
+        ctr.apply_keystream(255 * 4 + 2, &mut [0u8; 4]);
+    }
+}
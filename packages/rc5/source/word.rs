@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// Binary expansions of `e - 2` and `φ - 1`, truncated to 128 bits. The
+/// magic constants `P_w` and `Q_w` are their top `w` bits rounded to the
+/// nearest odd number, which is setting the lowest bit as the expansions go
+/// on past any width.
+const E_FRACTION: u128 = 0xb7e1_5162_8aed_2a6a_bf71_5880_9cf4_f3c7;
+const PHI_FRACTION: u128 = 0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835;
+
+/// Unsigned word of RC5-w, `w` being its number of bits.
+pub trait Word: Copy + Default + fmt::Debug + PartialEq {
+    const BITS: u32;
+    const BYTES: usize = Self::BITS as usize / 8;
+
+    /// `P_w`, the first entry of the key table before mixing in the key.
+    const P: Self;
+    /// `Q_w`, the step between the entries of the key table.
+    const Q: Self;
+
+    fn wrapping_add(self, other: Self) -> Self;
+    fn wrapping_sub(self, other: Self) -> Self;
+    fn xor(self, other: Self) -> Self;
+
+    /// Rotates left by the low `log2(w)` bits of `amount`.
+    fn rotl(self, amount: Self) -> Self;
+    fn rotr(self, amount: Self) -> Self;
+
+    fn from_u8(byte: u8) -> Self;
+
+    /// # Panics
+    ///
+    /// When `bytes` is not [`Word::BYTES`] long, likewise for `write_le`.
+    fn read_le(bytes: &[u8]) -> Self;
+    fn write_le(self, bytes: &mut [u8]);
+}
+
+macro_rules! implement_word {
+    ($($type:ty),*) => {$(
+        impl Word for $type {
+            const BITS: u32 = <$type>::BITS;
+
+            const P: Self = ((E_FRACTION >> (128 - <$type>::BITS)) | 1) as $type;
+            const Q: Self = ((PHI_FRACTION >> (128 - <$type>::BITS)) | 1) as $type;
+
+            fn wrapping_add(self, other: Self) -> Self {
+                <$type>::wrapping_add(self, other)
+            }
+
+            fn wrapping_sub(self, other: Self) -> Self {
+                <$type>::wrapping_sub(self, other)
+            }
+
+            fn xor(self, other: Self) -> Self {
+                self ^ other
+            }
+
+            fn rotl(self, amount: Self) -> Self {
+                self.rotate_left((amount % Self::BITS as $type) as u32)
+            }
+
+            fn rotr(self, amount: Self) -> Self {
+                self.rotate_right((amount % Self::BITS as $type) as u32)
+            }
+
+            fn from_u8(byte: u8) -> Self {
+                byte as $type
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                <$type>::from_le_bytes(bytes.try_into().expect("Wrong word length"))
+            }
+
+            fn write_le(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+implement_word!(u8, u16, u32, u64, u128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_constants() {
+        assert_eq!((0xb7, 0x9f), (u8::P, u8::Q));
+        assert_eq!((0xb7e1, 0x9e37), (u16::P, u16::Q));
+        assert_eq!((0xb7e1_5163, 0x9e37_79b9), (u32::P, u32::Q));
+        assert_eq!(
+            (0xb7e1_5162_8aed_2a6b, 0x9e37_79b9_7f4a_7c15),
+            (u64::P, u64::Q)
+        );
+        assert_eq!((E_FRACTION, PHI_FRACTION), (u128::P, u128::Q));
+    }
+}
@@ -0,0 +1,264 @@
+//! From-scratch RIPEMD-160 as specified by Dobbertin, Bosselaers and Preneel.
+//!
+//! The compression function runs two lines of five rounds in parallel over
+//! the same block, with different message orders, rotations and constants,
+//! and mixes both results into the chaining value.
+
+use std::fmt;
+
+use hasher::BlockBuffer;
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Digest(pub [u8; 20]);
+
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, formatter)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// Message word of every step, left line.
+const R_LEFT: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8, //
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, //
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2, //
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+
+/// Message word of every step, right line.
+const R_RIGHT: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, //
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2, //
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, //
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14, //
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+
+/// Rotation of every step, left line.
+const S_LEFT: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, //
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12, //
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, //
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, //
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+
+/// Rotation of every step, right line.
+const S_RIGHT: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, //
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11, //
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, //
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8, //
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+/// Additive constants of the five rounds, square and cube roots of 2, 3, 5
+/// and 7 for the left and right lines respectively.
+const K_LEFT: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+const K_RIGHT: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+/// Boolean function of round `round`, the right line uses them in reverse.
+fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+    match round {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z),
+    }
+}
+
+/// One line of 80 steps over the registers `[a, b, c, d, e]`.
+fn line(
+    mut registers: [u32; 5],
+    x: &[u32; 16],
+    order: &[usize; 80],
+    shifts: &[u32; 80],
+    constants: &[u32; 5],
+    function_of_round: impl Fn(usize) -> usize,
+) -> [u32; 5] {
+    for step in 0..80 {
+        let round = step / 16;
+        let [a, b, c, d, e] = registers;
+
+        let t = a
+            .wrapping_add(f(function_of_round(round), b, c, d))
+            .wrapping_add(x[order[step]])
+            .wrapping_add(constants[round])
+            .rotate_left(shifts[step])
+            .wrapping_add(e);
+
+        registers = [e, t, b, c.rotate_left(10), d];
+    }
+
+    registers
+}
+
+fn compress(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut x = [0u32; 16];
+
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let [al, bl, cl, dl, el] = line(*state, &x, &R_LEFT, &S_LEFT, &K_LEFT, |round| round);
+    let [ar, br, cr, dr, er] = line(*state, &x, &R_RIGHT, &S_RIGHT, &K_RIGHT, |round| 4 - round);
+    let [h0, h1, h2, h3, h4] = *state;
+
+    *state = [
+        h1.wrapping_add(cl).wrapping_add(dr),
+        h2.wrapping_add(dl).wrapping_add(er),
+        h3.wrapping_add(el).wrapping_add(ar),
+        h4.wrapping_add(al).wrapping_add(br),
+        h0.wrapping_add(bl).wrapping_add(cr),
+    ];
+}
+
+/// Incremental RIPEMD-160 hasher.
+#[derive(Clone, Debug)]
+pub struct Ripemd160 {
+    state: [u32; 5],
+    buffer: BlockBuffer<64>,
+}
+
+impl Default for Ripemd160 {
+    fn default() -> Self {
+        Ripemd160::new()
+    }
+}
+
+impl Ripemd160 {
+    pub fn new() -> Self {
+        Ripemd160 {
+            state: INITIAL_STATE,
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> Digest {
+        let data_len_bits = (self.buffer.length() as u64).wrapping_mul(8);
+        let state = &mut self.state;
+        self.buffer
+            .pad(&data_len_bits.to_le_bytes(), |block| compress(state, block));
+
+        let mut result = [0u8; 20];
+        for (bytes, word) in result.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        Digest(result)
+    }
+}
+
+impl hasher::Hasher for Ripemd160 {
+    const BLOCK_SIZE: usize = 64;
+
+    type Output = Digest;
+
+    fn new() -> Self {
+        Ripemd160::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Ripemd160::update(self, data)
+    }
+
+    fn finalize(self) -> Digest {
+        Ripemd160::finalize(self)
+    }
+}
+
+pub fn digest(input: &[u8]) -> Digest {
+    let mut hasher = Ripemd160::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_test_vectors() {
+        let test_cases: &[(&[u8], &str)] = &[
+            (b"", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            (b"a", "0bdc9d2d256b3ee9daae347be6f4dc835a467ffe"),
+            (b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            (
+                b"message digest",
+                "5d0689ef49d2fae572b881b123a85ffa21595f36",
+            ),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                "f71c27109c692c1b56bbdceb5b9d2865b3708dbc",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "12a053384a9c0c88e405a06c27dcf49ada62eb2b",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "b0e20b6e3116640286ed3a87a5713079b21f5189",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "9b752e45573d4b39f4dbd3323cab82bf63326bfb",
+            ),
+            (
+                &[b'a'; 1_000_000],
+                "52783243c1697bdbe16d37f97f68f08325dc1528",
+            ),
+        ];
+
+        for &(input, expected) in test_cases {
+            assert_eq!(expected, digest(input).to_string());
+        }
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let data: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let expected = digest(&data);
+
+        for chunk_len in 1..=130 {
+            let mut hasher = Ripemd160::new();
+
+            for chunk in data.chunks(chunk_len) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(expected, hasher.finalize());
+        }
+    }
+
+    #[test]
+    fn hmac_ripemd160() {
+        // RFC 2286 test case 2
+        let tag = hasher::Hmac::<Ripemd160>::mac(b"Jefe", b"what do ya want for nothing?");
+
+        assert_eq!("dda6c0213a485a9e24f4742064a7f033b43c4069", tag.to_string());
+    }
+}
@@ -0,0 +1,87 @@
+//! From-scratch SHA-1 and SHA-2 hash functions as specified in FIPS 180-4.
+
+use std::fmt;
+
+pub mod sha1;
+pub mod sha256;
+pub mod sha512;
+
+pub use sha1::Sha1;
+pub use sha256::{Sha224, Sha256};
+pub use sha512::{Sha384, Sha512};
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Digest<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> fmt::LowerHex for Digest<N> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Display for Digest<N> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, formatter)
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Digest<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Defines a public hasher around a core started from `$initial_state`, the
+/// digest being the first `$size` bytes of the final big-endian state.
+macro_rules! define_hasher {
+    ($name:ident, $core:ident, $initial_state:expr, $size:expr, $block_size:expr) => {
+        #[derive(Clone, Debug)]
+        pub struct $name($core);
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::new()
+            }
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                $name($core::new($initial_state))
+            }
+
+            pub fn update(&mut self, data: &[u8]) {
+                self.0.update(data)
+            }
+
+            pub fn finalize(self) -> $crate::Digest<$size> {
+                let mut result = [0u8; $size];
+                self.0.finalize(&mut result);
+
+                $crate::Digest(result)
+            }
+        }
+
+        impl hasher::Hasher for $name {
+            const BLOCK_SIZE: usize = $block_size;
+
+            type Output = $crate::Digest<$size>;
+
+            fn new() -> Self {
+                $name::new()
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                $name::update(self, data)
+            }
+
+            fn finalize(self) -> Self::Output {
+                $name::finalize(self)
+            }
+        }
+    };
+}
+
+pub(crate) use define_hasher;
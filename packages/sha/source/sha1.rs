@@ -0,0 +1,132 @@
+use hasher::BlockBuffer;
+
+use crate::{define_hasher, Digest};
+
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+fn compress(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut w = [0u32; 80];
+
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+
+    for t in 16..80 {
+        w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+    for (t, &word) in w.iter().enumerate() {
+        let (f, k) = match t {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+
+        (a, b, c, d, e) = (temp, a, b.rotate_left(30), c, d);
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Sha1Core {
+    state: [u32; 5],
+    buffer: BlockBuffer<64>,
+}
+
+impl Sha1Core {
+    fn new(initial_state: [u32; 5]) -> Self {
+        Sha1Core {
+            state: initial_state,
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| compress(state, block));
+    }
+
+    fn finalize(mut self, result: &mut [u8]) {
+        let data_len_bits = (self.buffer.length() as u64).wrapping_mul(8);
+        let state = &mut self.state;
+        self.buffer
+            .pad(&data_len_bits.to_be_bytes(), |block| compress(state, block));
+
+        for (bytes, word) in result.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+}
+
+define_hasher!(Sha1, Sha1Core, INITIAL_STATE, 20, 64);
+
+pub fn digest(input: &[u8]) -> Digest<20> {
+    let mut hasher = Sha1::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fips_180_4_examples() {
+        let test_cases: &[(&[u8], &str)] = &[
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+            (
+                &[b'a'; 1_000_000],
+                "34aa973cd4c4daa4f61eeb2bdbad27316534016f",
+            ),
+        ];
+
+        for &(input, expected) in test_cases {
+            assert_eq!(expected, digest(input).to_string());
+        }
+    }
+
+    #[test]
+    fn nist_byte_oriented_vectors() {
+        let test_cases: &[(&[u8], &str)] = &[
+            (&[], "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (&[0x36], "c1dfd96eea8cc2b62785275bca38ac261256e278"),
+            (&[0x19, 0x5a], "0a1c2d555bbe431ad6288af5a54f93e0449c9232"),
+            (
+                &[0xdf, 0x4b, 0xd2],
+                "bf36ed5d74727dfd5d7854ec6b1d49468d8ee8aa",
+            ),
+            (
+                &[0x54, 0x9e, 0x95, 0x9e],
+                "b78bae6d14338ffccfd5d5b5674a275f6ef9c717",
+            ),
+        ];
+
+        for &(input, expected) in test_cases {
+            assert_eq!(expected, digest(input).to_string());
+        }
+    }
+
+    #[test]
+    fn hmac_sha1() {
+        let tag = hasher::Hmac::<Sha1>::mac(b"Jefe", b"what do ya want for nothing?");
+
+        assert_eq!("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79", tag.to_string());
+    }
+}
@@ -0,0 +1,214 @@
+use hasher::BlockBuffer;
+
+use crate::{define_hasher, Digest};
+
+const INITIAL_STATE_224: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+const INITIAL_STATE_256: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    let mut w = [0u32; 64];
+
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+
+    for t in 16..64 {
+        let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+        let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+        w[t] = w[t - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[t - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for (&k, &word) in K.iter().zip(w.iter()) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+
+        (a, b, c, d, e, f, g, h) = (
+            temp1.wrapping_add(temp2),
+            a,
+            b,
+            c,
+            d.wrapping_add(temp1),
+            e,
+            f,
+            g,
+        );
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Sha256Core {
+    state: [u32; 8],
+    buffer: BlockBuffer<64>,
+}
+
+impl Sha256Core {
+    fn new(initial_state: [u32; 8]) -> Self {
+        Sha256Core {
+            state: initial_state,
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| compress(state, block));
+    }
+
+    fn finalize(mut self, result: &mut [u8]) {
+        let data_len_bits = (self.buffer.length() as u64).wrapping_mul(8);
+        let state = &mut self.state;
+        self.buffer
+            .pad(&data_len_bits.to_be_bytes(), |block| compress(state, block));
+
+        let mut bytes = [0u8; 32];
+
+        for (bytes, word) in bytes.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        result.copy_from_slice(&bytes[..result.len()]);
+    }
+}
+
+define_hasher!(Sha224, Sha256Core, INITIAL_STATE_224, 28, 64);
+define_hasher!(Sha256, Sha256Core, INITIAL_STATE_256, 32, 64);
+
+pub fn digest_224(input: &[u8]) -> Digest<28> {
+    let mut hasher = Sha224::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+pub fn digest(input: &[u8]) -> Digest<32> {
+    let mut hasher = Sha256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fips_180_4_examples() {
+        let test_cases: &[(&[u8], &str, &str)] = &[
+            (
+                b"abc",
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                &[b'a'; 1_000_000],
+                "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67",
+                "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+            ),
+        ];
+
+        for &(input, expected_224, expected_256) in test_cases {
+            assert_eq!(expected_224, digest_224(input).to_string());
+            assert_eq!(expected_256, digest(input).to_string());
+        }
+    }
+
+    #[test]
+    fn nist_byte_oriented_vectors() {
+        let test_cases_224: &[(&[u8], &str)] = &[
+            (
+                &[],
+                "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+            ),
+            (
+                &[0x84],
+                "3cd36921df5d6963e73739cf4d20211e2d8877c19cff087ade9d0e3a",
+            ),
+            (
+                &[0x5c, 0x7b],
+                "daff9bce685eb831f97fc1225b03c275a6c112e2d6e76f5faf7a36e6",
+            ),
+        ];
+
+        let test_cases_256: &[(&[u8], &str)] = &[
+            (
+                &[],
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                &[0xd3],
+                "28969cdfa74a12c82f3bad960b0b000aca2ac329deea5c2328ebc6f2ba9802c1",
+            ),
+            (
+                &[0x11, 0xaf],
+                "5ca7133fa735326081558ac312c620eeca9970d1e70a4b95533d956f072d1f98",
+            ),
+            (
+                &[0xb4, 0x19, 0x0e],
+                "dff2e73091f6c05e528896c4c831b9448653dc2ff043528f6769437bc7b975c2",
+            ),
+            (
+                &[0x74, 0xba, 0x25, 0x21],
+                "b16aa56be3880d18cd41e68384cf1ec8c17680c45a02b1575dc1518923ae8b0e",
+            ),
+        ];
+
+        for &(input, expected) in test_cases_224 {
+            assert_eq!(expected, digest_224(input).to_string());
+        }
+
+        for &(input, expected) in test_cases_256 {
+            assert_eq!(expected, digest(input).to_string());
+        }
+    }
+
+    #[test]
+    fn hmac_sha224_sha256() {
+        let key = b"Jefe";
+        let data = b"what do ya want for nothing?";
+
+        assert_eq!(
+            "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+            hasher::Hmac::<Sha224>::mac(key, data).to_string()
+        );
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            hasher::Hmac::<Sha256>::mac(key, data).to_string()
+        );
+    }
+}
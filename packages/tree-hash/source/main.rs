@@ -0,0 +1,225 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use hasher::encoding::Hex;
+use hasher::tree::Manifest;
+use hasher::Hasher;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+use std::process::ExitCode;
+
+/// Hash directory trees into Merkle manifests and verify trees against them
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct CliArgs {
+    #[command(subcommand)]
+    operation: Operation,
+}
+
+#[derive(Subcommand, Debug)]
+enum Operation {
+    Manifest(ManifestArgs),
+    Verify(VerifyArgs),
+}
+
+#[derive(Args, Debug)]
+struct ManifestArgs {
+    /// Root of the tree to hash
+    directory: String,
+
+    /// Hash function of the file digests and the Merkle tree
+    #[arg(short, long, value_enum, default_value_t = Algorithm::Md5)]
+    algorithm: Algorithm,
+
+    /// Write the manifest to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// Root of the tree to check
+    directory: String,
+
+    /// Manifest written by the `manifest` command
+    manifest: String,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Algorithm {
+    Md4,
+    Md5,
+    Ripemd160,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn name(self) -> String {
+        self.to_possible_value()
+            .expect("No variant is skipped")
+            .get_name()
+            .to_string()
+    }
+
+    fn build(self, directory: &str, exclude: &[&str]) -> io::Result<Manifest> {
+        fn build<H: Hasher>(name: &str, directory: &str, exclude: &[&str]) -> io::Result<Manifest> {
+            Manifest::build::<H>(name, Path::new(directory), exclude)
+        }
+
+        let name = self.name();
+        match self {
+            Algorithm::Md4 => build::<md4::Md4>(&name, directory, exclude),
+            Algorithm::Md5 => build::<md5::Md5>(&name, directory, exclude),
+            Algorithm::Ripemd160 => build::<ripemd160::Ripemd160>(&name, directory, exclude),
+            Algorithm::Sha1 => build::<sha::Sha1>(&name, directory, exclude),
+            Algorithm::Sha224 => build::<sha::Sha224>(&name, directory, exclude),
+            Algorithm::Sha256 => build::<sha::Sha256>(&name, directory, exclude),
+            Algorithm::Sha384 => build::<sha::Sha384>(&name, directory, exclude),
+            Algorithm::Sha512 => build::<sha::Sha512>(&name, directory, exclude),
+        }
+    }
+
+    fn has_valid_root(self, manifest: &Manifest) -> bool {
+        match self {
+            Algorithm::Md4 => manifest.has_valid_root::<md4::Md4>(),
+            Algorithm::Md5 => manifest.has_valid_root::<md5::Md5>(),
+            Algorithm::Ripemd160 => manifest.has_valid_root::<ripemd160::Ripemd160>(),
+            Algorithm::Sha1 => manifest.has_valid_root::<sha::Sha1>(),
+            Algorithm::Sha224 => manifest.has_valid_root::<sha::Sha224>(),
+            Algorithm::Sha256 => manifest.has_valid_root::<sha::Sha256>(),
+            Algorithm::Sha384 => manifest.has_valid_root::<sha::Sha384>(),
+            Algorithm::Sha512 => manifest.has_valid_root::<sha::Sha512>(),
+        }
+    }
+}
+
+/// Path of `file` relative to `directory` when it lies inside the tree, so a
+/// manifest stored in the tree it describes is not hashed itself.
+fn path_in_tree(directory: &str, file: &str) -> Option<String> {
+    let file = Path::new(file);
+    let parent = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let directory = fs::canonicalize(directory).ok()?;
+    let file = fs::canonicalize(parent).ok()?.join(file.file_name()?);
+    let relative = file.strip_prefix(directory).ok()?;
+
+    let components = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<&str>>>()?;
+
+    Some(components.join("/"))
+}
+
+fn manifest(args: ManifestArgs) -> Result<(), String> {
+    let excluded = args
+        .output
+        .as_deref()
+        .and_then(|output| path_in_tree(&args.directory, output));
+    let exclude: Vec<&str> = excluded.iter().map(String::as_str).collect();
+
+    let manifest = args
+        .algorithm
+        .build(&args.directory, &exclude)
+        .map_err(|error| format!("{}: {}", args.directory, error))?;
+
+    match &args.output {
+        Some(output) => fs::write(output, manifest.to_string())
+            .map_err(|error| format!("{}: {}", output, error))?,
+        None => print!("{}", manifest),
+    }
+
+    eprintln!(
+        "tree-hash: {}: {} files, root {}",
+        args.directory,
+        manifest.files().len(),
+        Hex(manifest.root())
+    );
+
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<bool, String> {
+    let text = fs::read_to_string(&args.manifest)
+        .map_err(|error| format!("{}: {}", args.manifest, error))?;
+    let expected =
+        Manifest::parse(&text).map_err(|error| format!("{}: {}", args.manifest, error))?;
+
+    let algorithm = Algorithm::from_str(expected.algorithm(), true).map_err(|_| {
+        format!(
+            "{}: unknown algorithm {}",
+            args.manifest,
+            expected.algorithm()
+        )
+    })?;
+
+    if !algorithm.has_valid_root(&expected) {
+        return Err(format!(
+            "{}: root does not match the listed files, the manifest is corrupted",
+            args.manifest
+        ));
+    }
+
+    let excluded = path_in_tree(&args.directory, &args.manifest);
+    let exclude: Vec<&str> = excluded.iter().map(String::as_str).collect();
+
+    let current = algorithm
+        .build(&args.directory, &exclude)
+        .map_err(|error| format!("{}: {}", args.directory, error))?;
+    let changes = expected.compare(&current);
+
+    for path in &changes.added {
+        println!("added: {}", path);
+    }
+    for path in &changes.removed {
+        println!("removed: {}", path);
+    }
+    for path in &changes.modified {
+        println!("modified: {}", path);
+    }
+
+    match changes.is_empty() {
+        true => eprintln!(
+            "tree-hash: {}: OK, {} files, root {}",
+            args.directory,
+            current.files().len(),
+            Hex(current.root())
+        ),
+        false => eprintln!(
+            "tree-hash: {}: {} added, {} removed, {} modified",
+            args.directory,
+            changes.added.len(),
+            changes.removed.len(),
+            changes.modified.len()
+        ),
+    }
+
+    Ok(changes.is_empty())
+}
+
+fn main() -> ExitCode {
+    let cli_args = CliArgs::parse();
+
+    let result = match cli_args.operation {
+        Operation::Manifest(args) => manifest(args).map(|()| true),
+        Operation::Verify(args) => verify(args),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("tree-hash: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
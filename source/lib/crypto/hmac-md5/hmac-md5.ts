@@ -0,0 +1,32 @@
+import { FFIType, includeNative } from "lib/ffi";
+import { toBytes, toHex } from "lib/crypto/md5/md5";
+
+type HmacMd5Fn = (
+  key: string | Uint8Array,
+  message: string | Uint8Array
+) => string;
+
+const { md5_hmac_digest } = includeNative("md5-ffi", {
+  md5_hmac_digest: {
+    args: [FFIType.ptr, FFIType.u64, FFIType.ptr, FFIType.u64, FFIType.ptr],
+    returns: FFIType.void,
+  },
+});
+
+const hmacMd5: HmacMd5Fn = (key, message) => {
+  const keyBytes = toBytes(key);
+  const messageBytes = toBytes(message);
+  const tag = new Uint8Array(16);
+
+  md5_hmac_digest(
+    keyBytes,
+    keyBytes.length,
+    messageBytes,
+    messageBytes.length,
+    tag
+  );
+
+  return toHex(tag);
+};
+
+export default hmacMd5;
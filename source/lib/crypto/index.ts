@@ -1 +1,7 @@
-export { default as md5 } from "./md5";
+export {
+  default as md5,
+  md5File,
+  Md5Hasher,
+  type Md5FileProgress,
+} from "./md5";
+export { default as hmacMd5 } from "./hmac-md5";
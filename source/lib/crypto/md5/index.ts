@@ -1 +1,6 @@
-export { default } from "./md5";
+export {
+  default,
+  md5File,
+  Md5Hasher,
+  type Md5FileProgress,
+} from "./md5";
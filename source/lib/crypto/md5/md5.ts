@@ -1,16 +1,136 @@
-import { FFIType, includeNative } from "lib/ffi";
+import { FFIType, includeNative, JSCallback, type Pointer } from "lib/ffi";
 
-type Md5HashFn = (message: string) => string;
+type Md5Input = string | Uint8Array;
 
-const md5: Md5HashFn = (message: string) => {
-  const { md5 } = includeNative("md5", {
-    md5: {
-      args: [FFIType.cstring],
-      returns: FFIType.cstring,
-    },
-  });
+type Md5HashFn = (message: Md5Input) => string;
 
-  return md5(Buffer.from(message.concat("\0"), "utf8")).toString();
+// Receives the bytes hashed so far and the file size, returns false to cancel.
+export type Md5FileProgress = (processed: number, total: number) => boolean;
+
+const DIGEST_SIZE = 16;
+
+// Status codes of the native `md5_file`.
+const Md5FileStatus = {
+  Ok: 0,
+  Cancelled: 1,
+  InvalidPath: 2,
+  IoError: 3,
+} as const;
+
+const native = includeNative("md5-ffi", {
+  md5_digest: {
+    args: [FFIType.ptr, FFIType.u64, FFIType.ptr],
+    returns: FFIType.void,
+  },
+  md5_hasher_new: {
+    args: [],
+    returns: FFIType.ptr,
+  },
+  md5_hasher_update: {
+    args: [FFIType.ptr, FFIType.ptr, FFIType.u64],
+    returns: FFIType.void,
+  },
+  md5_hasher_finalize: {
+    args: [FFIType.ptr, FFIType.ptr],
+    returns: FFIType.void,
+  },
+  md5_hasher_free: {
+    args: [FFIType.ptr],
+    returns: FFIType.void,
+  },
+  md5_file: {
+    args: [FFIType.ptr, FFIType.function, FFIType.ptr],
+    returns: FFIType.i32,
+  },
+});
+
+export function toBytes(input: Md5Input): Uint8Array {
+  return typeof input === "string" ? Buffer.from(input, "utf8") : input;
+}
+
+export function toHex(digest: Uint8Array): string {
+  return Buffer.from(digest).toString("hex");
+}
+
+const md5: Md5HashFn = (message: Md5Input) => {
+  const bytes = toBytes(message);
+  const digest = new Uint8Array(DIGEST_SIZE);
+
+  native.md5_digest(bytes, bytes.length, digest);
+
+  return toHex(digest);
 };
 
+// Hashes a file natively in 1 MiB chunks without loading it in memory.
+// Returns null when `onProgress` cancels. The call blocks until the whole file
+// is hashed, run it in a worker to keep the event loop responsive.
+export function md5File(
+  filePath: string,
+  onProgress?: Md5FileProgress
+): string | null {
+  const digest = new Uint8Array(DIGEST_SIZE);
+  const callback =
+    onProgress &&
+    new JSCallback(
+      (processed: number | bigint, total: number | bigint) =>
+        onProgress(Number(processed), Number(total)),
+      { args: [FFIType.u64, FFIType.u64], returns: FFIType.bool }
+    );
+
+  try {
+    const status = native.md5_file(
+      Buffer.from(`${filePath}\0`, "utf8"),
+      callback?.ptr ?? null,
+      digest
+    );
+
+    switch (status) {
+      case Md5FileStatus.Ok:
+        return toHex(digest);
+      case Md5FileStatus.Cancelled:
+        return null;
+      case Md5FileStatus.InvalidPath:
+        throw new Error(`Invalid file path: ${filePath}`);
+      default:
+        throw new Error(`Cannot read file: ${filePath}`);
+    }
+  } finally {
+    callback?.close();
+  }
+}
+
+// Incremental hashing for inputs that do not fit in memory at once. The
+// native hasher is released by `digest` or `dispose`.
+export class Md5Hasher {
+  private handle: Pointer | null = native.md5_hasher_new();
+
+  private get liveHandle(): Pointer {
+    if (this.handle === null) {
+      throw new Error("MD5 hasher has already been finalized or disposed");
+    }
+
+    return this.handle;
+  }
+
+  public update(data: Md5Input): this {
+    const bytes = toBytes(data);
+    native.md5_hasher_update(this.liveHandle, bytes, bytes.length);
+
+    return this;
+  }
+
+  public digest(): string {
+    const digest = new Uint8Array(DIGEST_SIZE);
+    native.md5_hasher_finalize(this.liveHandle, digest);
+    this.handle = null;
+
+    return toHex(digest);
+  }
+
+  public dispose(): void {
+    native.md5_hasher_free(this.handle);
+    this.handle = null;
+  }
+}
+
 export default md5;
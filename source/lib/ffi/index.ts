@@ -1,2 +1,2 @@
 export { includeNative } from "./include";
-export { FFIType } from "bun:ffi";
+export { FFIType, JSCallback, type Pointer } from "bun:ffi";
@@ -5,6 +5,7 @@ import chalk from "chalk";
 
 import { md5 } from "lib/crypto";
 import { removeEdgeQuotes } from "lib/string";
+import type { Md5FileMessage, Md5FileRequest } from "./md5-file-worker";
 
 type PromptResponse =
   | { messageSource: "string"; messageContent: string }
@@ -88,5 +89,77 @@ if (!(await file.exists())) {
   process.exit(1);
 }
 
-const fileContent = await file.text();
-logMessageDigest(md5(fileContent));
+const CTRL_C = "\u0003";
+
+function formatMebibytes(bytes: number): string {
+  return `${(bytes / 2 ** 20).toFixed(1)} MiB`;
+}
+
+function renderProgress(processed: number, total: number): void {
+  const percent = total > 0 ? ((processed / total) * 100).toFixed(1) : "?";
+
+  process.stdout.write(
+    `\r Hashing: ${chalk.cyanBright(`${percent}%`)} ` +
+      `(${formatMebibytes(processed)} / ${formatMebibytes(total)})`
+  );
+}
+
+const cancelFlag = new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT);
+
+function cancelHashing(): void {
+  Atomics.store(new Int32Array(cancelFlag), 0, 1);
+}
+
+function onKey(key: Buffer): void {
+  const char = key.toString();
+
+  if (char === "q" || char === CTRL_C) {
+    cancelHashing();
+  }
+}
+
+if (process.stdin.isTTY) {
+  process.stdin.setRawMode(true);
+  process.stdin.resume();
+  process.stdin.on("data", onKey);
+  console.log(chalk.gray("\n Press q or Ctrl+C to cancel"));
+}
+
+const worker = new Worker(new URL("./md5-file-worker.ts", import.meta.url).href);
+
+const digest = await new Promise<string | null>((resolve, reject) => {
+  worker.onmessage = ({ data }: MessageEvent<Md5FileMessage>) => {
+    switch (data.type) {
+      case "progress":
+        renderProgress(data.processed, data.total);
+        break;
+      case "done":
+        resolve(data.digest);
+        break;
+      case "error":
+        reject(new Error(data.message));
+        break;
+    }
+  };
+
+  worker.postMessage({
+    filePath: promptResponse.filePath,
+    cancelFlag,
+  } satisfies Md5FileRequest);
+}).finally(() => {
+  worker.terminate();
+
+  if (process.stdin.isTTY) {
+    process.stdin.off("data", onKey);
+    process.stdin.setRawMode(false);
+    process.stdin.pause();
+  }
+});
+
+if (digest === null) {
+  console.error(chalk.yellowBright("\n Hashing cancelled"));
+
+  process.exit(1);
+}
+
+logMessageDigest(digest);
@@ -0,0 +1,39 @@
+import { md5File } from "lib/crypto";
+
+// Native hashing blocks its thread, so it runs here while the main thread
+// renders progress and listens for cancellation. Setting the first element of
+// `cancelFlag` to 1 cancels at the next chunk.
+export type Md5FileRequest = {
+  filePath: string;
+  cancelFlag: SharedArrayBuffer;
+};
+
+export type Md5FileMessage =
+  | { type: "progress"; processed: number; total: number }
+  | { type: "done"; digest: string | null }
+  | { type: "error"; message: string };
+
+declare var self: Worker;
+
+function post(message: Md5FileMessage): void {
+  self.postMessage(message);
+}
+
+self.onmessage = ({ data }: MessageEvent<Md5FileRequest>) => {
+  const cancelFlag = new Int32Array(data.cancelFlag);
+
+  try {
+    const digest = md5File(data.filePath, (processed, total) => {
+      post({ type: "progress", processed, total });
+
+      return Atomics.load(cancelFlag, 0) === 0;
+    });
+
+    post({ type: "done", digest });
+  } catch (error) {
+    post({
+      type: "error",
+      message: error instanceof Error ? error.message : String(error),
+    });
+  }
+};